@@ -275,7 +275,9 @@ app.controller('movieCtrl', function($scope, $http, $sce, $compile, $rootScope)
     var personCount = {};
     var locationList = {};
     var locationListBoolean = new Array ();
-    jsonData.forEach(function(scene) {
+    // older scripts are only the array of scenes without metadata
+    var scenes = Array.isArray(jsonData) ? jsonData : jsonData.scenes;
+    scenes.forEach(function(scene) {
       // create scenen titel
       masterHtml += $scope.createScene( sceneIndex );
       sceneIndex++;
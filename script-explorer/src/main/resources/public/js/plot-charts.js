@@ -59,7 +59,9 @@ function get_json(url, cb) {
 }
 
 function get_locations(script) {
-  return script.reduce(function(locations, scene) {
+  // older scripts are only the array of scenes without metadata
+  var scenes = Array.isArray(script) ? script : script.scenes;
+  return scenes.reduce(function(locations, scene) {
     return locations.concat(scene);
   });
 }
@@ -68,8 +70,7 @@ function most_frequent_characters(locations, num_chars) {
   var num_chars = num_chars || 5;
   var occurrences = {};
   locations.forEach(function(loc) {
-    loc.parts
-      .filter(is_dialog)
+    get_dialogs(loc)
       .forEach(function(dialog) {
         var speaker = normalize_name(dialog.character);
         occurrences[speaker] = (occurrences[speaker] || 0) + 1
@@ -88,10 +89,20 @@ function is_dialog(part) {
   return part.dialog != null;
 }
 
+// both dialogs of a dual dialog count as dialogs of their own
+function get_dialogs(loc) {
+  return loc.parts.reduce(function(dialogs, part) {
+    if (part.dual_dialog != null) {
+      return dialogs.concat(part.dual_dialog);
+    }
+    return is_dialog(part) ? dialogs.concat([part]) : dialogs;
+  }, []);
+}
+
 function get_characters(loc) {
-  return loc.parts
+  return get_dialogs(loc)
     .filter(function(part) {
-      return part.dialog != null && part.character.trim() != ""
+      return part.character.trim() != ""
     })
     .map(function(part) {
       return normalize_name(part.character)
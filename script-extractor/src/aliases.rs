@@ -0,0 +1,262 @@
+//! Mapping the different names of a character to one canonical name.
+//!
+//! Scripts often refer to the same character by different names, like
+//! `SHERIFF WOODY` and `WOODY`, which `normalize_name` cannot unify. These
+//! variants can be given as `Aliases`, which map them to a canonical name
+//! and can be stored as `toml` or `json`. Likely aliases can be suggested
+//! based on the similarity of the names and the locations the characters
+//! speak in.
+//!
+//! # Example
+//!
+//! ```toml
+//! WOODY = ["SHERIFF WOODY", "WOODIE"]
+//! "MR. POTATO HEAD" = ["POTATO HEAD"]
+//! ```
+
+/// The canonical names of the variants of characters' names.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Aliases {
+    /// The canonical names by the normalized names of their variants
+    pub names: BTreeMap<String, String>,
+}
+
+/// A suggestion that a character is another one speaking under a different
+/// name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AliasSuggestion {
+    /// The normalized name of the character with more lines
+    pub canonical: String,
+    /// The normalized name suggested as alias of `canonical`
+    pub alias: String,
+    /// The edit distance between both names
+    pub distance: usize,
+}
+
+impl Aliases {
+    /// Adds a variant of the given canonical name.
+    pub fn add(&mut self, variant: &str, canonical: &str) {
+        self.names.insert(normalize_name(variant), canonical.to_string());
+    }
+
+    /// Returns the canonical name of the given name, if it is a variant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use script_extractor::aliases::Aliases;
+    /// let mut aliases: Aliases = Default::default();
+    /// aliases.add("Sheriff Woody", "WOODY");
+    ///
+    /// assert_eq!(aliases.canonical_name("SHERIFF WOODY (V.O.)"), Some("WOODY"));
+    /// assert_eq!(aliases.canonical_name("BUZZ"), None);
+    /// ```
+    pub fn canonical_name(&self, name: &str) -> Option<&str> {
+        self.names.get(&normalize_name(name)).map(|canonical| canonical.as_ref())
+    }
+
+    /// Reads `Aliases` from `toml`.
+    ///
+    /// Each key is a canonical name and its value the list of its variants,
+    /// or a single variant.
+    pub fn from_toml(input: &str) -> Result<Aliases, ParseError> {
+        let names = try!(toml::from_str(input).map_err(|error| {
+            ParseError::InvalidStructure { message: error.to_string() }
+        }));
+        collect_aliases(names)
+    }
+
+    /// Reads `Aliases` from `json`.
+    ///
+    /// The object has the same structure as the `toml`, like
+    /// `{ "WOODY": ["SHERIFF WOODY"] }`.
+    pub fn from_json(input: &str) -> Result<Aliases, ParseError> {
+        collect_aliases(try!(serde_json::from_str(input)))
+    }
+}
+
+/// Replaces the names in the character cues of all `Dialog`s by their
+/// canonical names.
+///
+/// A cue naming several characters is written again with its names joined
+/// by `&`, like `WOODY & BUZZ` for `SHERIFF WOODY AND BUZZ`.
+///
+/// # Examples
+///
+/// ```
+/// # use script_extractor::aliases::{Aliases, apply_aliases};
+/// # use script_extractor::parse::fountain;
+/// let input = "INT. YARD - DAY\n\nSHERIFF WOODY (V.O.)\nHowdy.\n\nSHERIFF WOODY AND BUZZ\nReach for the sky.\n";
+/// let script = fountain::parse_script(&mut input.as_bytes()).unwrap();
+///
+/// let mut aliases: Aliases = Default::default();
+/// aliases.add("Sheriff Woody", "WOODY");
+/// let script = apply_aliases(script, &aliases);
+///
+/// let cues: Vec<String> = script.scenes[0][0].parts.iter()
+///                                            .flat_map(|part| part.dialogs())
+///                                            .map(|dialog| dialog.speaker.clone())
+///                                            .collect();
+/// assert_eq!(cues, vec!["WOODY", "WOODY & BUZZ"]);
+/// ```
+pub fn apply_aliases(mut script: Script, aliases: &Aliases) -> Script {
+    for location in script.scenes.iter_mut().flat_map(|scene| scene.iter_mut()) {
+        for part in location.parts.iter_mut() {
+            apply_part_aliases(part, aliases);
+        }
+    }
+
+    script
+}
+
+/// Suggests aliases for the characters of the given `Script`.
+///
+/// Two characters are likely the same if their names are similar, like
+/// `WOODY` and `WOODIE`, or one is part of the other, like `WOODY` and
+/// `SHERIFF WOODY`, but they never speak in the same location. Each alias is
+/// suggested for the most similar character with more lines, sorted by the
+/// edit distance of their names.
+pub fn suggest_aliases(script: &Script) -> Vec<AliasSuggestion> {
+    let characters = extract_characters(script);
+    let cooccurrences = compute_cooccurrences(script, Granularity::Location, Weighting::SharedScenes);
+    let index = |name: &String| cooccurrences.characters.binary_search(name).ok();
+
+    let mut suggestions: Vec<AliasSuggestion> = Vec::new();
+    for (i, alias) in characters.iter().enumerate() {
+        // the characters are sorted by their lines descending
+        let canonical = characters[..i].iter().filter(|canonical| {
+            let shares_location = match (index(&canonical.name), index(&alias.name)) {
+                (Some(a), Some(b)) => cooccurrences.matrix[a][b] > 0,
+                _ => false,
+            };
+            !shares_location && !suggestions.iter().any(|s| s.alias == canonical.name) &&
+            are_similar_names(&canonical.name, &alias.name)
+        }).min_by_key(|canonical| edit_distance(&canonical.name, &alias.name));
+
+        if let Some(canonical) = canonical {
+            suggestions.push(AliasSuggestion {
+                canonical: canonical.name.clone(),
+                alias: alias.name.clone(),
+                distance: edit_distance(&canonical.name, &alias.name),
+            });
+        }
+    }
+
+    suggestions.sort_by(|a, b| a.distance.cmp(&b.distance));
+    suggestions
+}
+
+/// Serialize the given suggestions into the `toml` read by
+/// `Aliases::from_toml`
+///
+/// # Example
+///
+/// ```toml
+/// WOODY = ["WOODIE", "SHERIFF WOODY"]
+/// ```
+pub fn format_toml<W: Write>(suggestions: &Vec<AliasSuggestion>, output: &mut W) -> io::Result<()> {
+    let mut table: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for suggestion in suggestions.iter() {
+        table.entry(&suggestion.canonical).or_insert(Vec::new()).push(&suggestion.alias);
+    }
+
+    let toml = try!(toml::to_string(&table).map_err(|error| io::Error::new(io::ErrorKind::Other, error)));
+    try!(write!(output, "{}", toml));
+    output.flush()
+}
+
+
+use ::{Dialog, ScenePart, Script};
+use characters::{extract_characters, normalize_name};
+use cooccurrence::{Granularity, Weighting, compute_cooccurrences};
+use parse::ParseError;
+use serde_json;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use toml;
+
+/// The variants of a canonical name, given as a single string or a list.
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "a string or an array of strings")]
+enum Variants {
+    Single(String),
+    List(Vec<String>),
+}
+
+fn collect_aliases(names: BTreeMap<String, Variants>) -> Result<Aliases, ParseError> {
+    let mut aliases: Aliases = Default::default();
+    for (canonical, variants) in names {
+        let variants = match variants {
+            Variants::Single(variant) => vec![variant],
+            Variants::List(variants) => variants,
+        };
+        if variants.is_empty() {
+            return Err(ParseError::InvalidStructure {
+                message: format!("expected the variants of '{}' to be a string or an array of strings", canonical),
+            });
+        }
+
+        for variant in variants.iter() {
+            aliases.add(variant, &canonical);
+        }
+    }
+
+    Ok(aliases)
+}
+
+fn apply_part_aliases(part: &mut ScenePart, aliases: &Aliases) {
+    match part {
+        &mut ScenePart::Dialog(ref mut dialog) => apply_dialog_aliases(dialog, aliases),
+        &mut ScenePart::DualDialog { ref mut left, ref mut right } => {
+            apply_dialog_aliases(left, aliases);
+            apply_dialog_aliases(right, aliases);
+        }
+        _ => {}
+    }
+}
+
+fn apply_dialog_aliases(dialog: &mut Dialog, aliases: &Aliases) {
+    let speakers = dialog.speakers();
+    if !speakers.iter().any(|speaker| aliases.canonical_name(speaker.name()).is_some()) {
+        return;
+    }
+
+    let names: Vec<&str> = speakers.iter()
+                                   .map(|speaker| aliases.canonical_name(speaker.name()).unwrap_or(speaker.name()))
+                                   .collect();
+    dialog.speaker = names.join(" & ");
+}
+
+/// Checks if two normalized names could be spellings of the same name.
+///
+/// Short names have to match more closely, so that `BOB` and `ROB` are
+/// similar, but `ANN` and `BOB` are not.
+fn are_similar_names(a: &str, b: &str) -> bool {
+    let is_part_of = |name: &str, other: &str| {
+        let words: Vec<&str> = other.split_whitespace().collect();
+        name.split_whitespace().all(|word| words.contains(&word))
+    };
+    let shortest = cmp::min(a.chars().count(), b.chars().count());
+    let distance = edit_distance(a, b);
+
+    is_part_of(a, b) || is_part_of(b, a) || (distance <= 2 && 2 * distance < shortest)
+}
+
+/// Computes the Levenshtein distance between two names.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+
+    let mut distances: Vec<usize> = (0..b.len() + 1).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut previous_diagonal = distances[0];
+        distances[0] = i + 1;
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = previous_diagonal + if a_char == b_char { 0 } else { 1 };
+            previous_diagonal = distances[j + 1];
+            distances[j + 1] = cmp::min(substitution, cmp::min(distances[j], distances[j + 1]) + 1);
+        }
+    }
+
+    distances[b.len()]
+}
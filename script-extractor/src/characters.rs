@@ -0,0 +1,219 @@
+//! Extracting the characters of a `Script`.
+//!
+//! The characters are collected from the speakers of all `Dialog`s,
+//! including both `Dialog`s of a `DualDialog`, so characters who never speak
+//! are not included. Each character of a cue like `WOODY AND BUZZ` is
+//! credited with the dialog, while groups like `CROWD` are left out.
+
+/// A character of a `Script` with statistics about its dialog.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Character {
+    /// The normalized name of the character (see `normalize_name`)
+    pub name: String,
+    /// The number of `Dialog`s of the character
+    pub lines: u32,
+    /// The number of spoken words, not counting inline directions
+    pub words: u32,
+    /// The page of the first `Dialog` of the character
+    pub first_page: u32,
+    /// The page of the last `Dialog` of the character
+    pub last_page: u32,
+    /// The indices of the `Scene`s in which the character speaks
+    pub scenes: Vec<usize>,
+    /// The names of the `Location`s in which the character speaks
+    pub locations: Vec<String>,
+    /// The different spellings of the name in the character cues, which
+    /// only contain the canonical name of a cue replaced by an alias
+    pub variants: Vec<String>,
+}
+
+/// Extracts all characters from the given `Script`.
+///
+/// Returns one `Character` per distinct normalized speaker, sorted by their
+/// number of lines descending.
+pub fn extract_characters(script: &Script) -> Vec<Character> {
+    let mut characters: BTreeMap<String, Character> = BTreeMap::new();
+
+    for (scene_index, scene) in script.scenes.iter().enumerate() {
+        for location in scene.iter() {
+            for dialog in location.parts.iter().flat_map(|part| part.dialogs()) {
+                let page = dialog.page;
+                for speaker in dialog.speakers().iter().filter(|speaker| !speaker.is_group()) {
+                    let name = normalize_name(speaker.name());
+                    if name.len() == 0 {
+                        continue;
+                    }
+
+                    let character = characters.entry(name.clone()).or_insert_with(|| {
+                        Character {
+                            name: name,
+                            first_page: page,
+                            ..Default::default()
+                        }
+                    });
+
+                    character.lines += 1;
+                    character.words += count_words(&dialog.dialog);
+                    character.last_page = page;
+
+                    if character.scenes.last() != Some(&scene_index) {
+                        character.scenes.push(scene_index);
+                    }
+                    if !character.locations.contains(&location.name) {
+                        character.locations.push(location.name.clone());
+                    }
+                    if !character.variants.iter().any(|v| v == speaker.name()) {
+                        character.variants.push(speaker.name().to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    let mut characters: Vec<Character> = characters.into_iter().map(|(_, c)| c).collect();
+    characters.sort_by(|a, b| b.lines.cmp(&a.lines));
+
+    characters
+}
+
+/// Normalizes the name of a speaker.
+///
+/// Surrounding whitespace and extensions like `(CONT'D)` are removed and the
+/// name is converted to uppercase. A voice like `WOODY'S VOICE` belongs to
+/// the character itself.
+///
+/// # Examples
+///
+/// ```
+/// # use script_extractor::characters::normalize_name;
+/// assert_eq!(normalize_name(" Sheriff  Woody "), "SHERIFF WOODY");
+/// assert_eq!(normalize_name("WOODY (CONT'D)"), "WOODY");
+/// assert_eq!(normalize_name("Woody's voice"), "WOODY");
+/// assert_eq!(normalize_name("JAMES’ VOICE (V.O.)"), "JAMES");
+/// ```
+pub fn normalize_name(speaker: &str) -> String {
+    let name = EXTENSIONS.replace_all(speaker, "");
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ").to_uppercase();
+    let character = VOICE.captures(&name).map(|captures| captures.at(1).unwrap().to_string());
+    character.unwrap_or(name)
+}
+
+/// Returns the normalized names of the characters speaking in the given
+/// `ScenePart`.
+///
+/// Every character of a cue is returned, and both speakers of a
+/// `DualDialog`, but no groups.
+///
+/// # Examples
+///
+/// ```
+/// # use script_extractor::{Dialog, ScenePart};
+/// # use script_extractor::characters::speaking_characters;
+/// let dialog = ScenePart::Dialog(Dialog {
+///     speaker: "Woody and Buzz".to_string(),
+///     mode: Vec::new(),
+///     dialog: Vec::new(),
+///     page: 1,
+///     end_page: 1,
+///     top: None,
+///     end_top: None,
+///     revised: false,
+///     source: Vec::new(),
+/// });
+/// assert_eq!(speaking_characters(&dialog), vec!["WOODY", "BUZZ"]);
+/// ```
+pub fn speaking_characters(part: &ScenePart) -> Vec<String> {
+    part.dialogs().into_iter().flat_map(dialog_characters).collect()
+}
+
+/// Returns the normalized names of the characters speaking the given
+/// `Dialog`, leaving out groups.
+pub fn dialog_characters(dialog: &Dialog) -> Vec<String> {
+    dialog.speakers()
+          .iter()
+          .filter(|speaker| !speaker.is_group())
+          .map(|speaker| normalize_name(speaker.name()))
+          .filter(|name| name.len() > 0)
+          .collect()
+}
+
+/// Serialize the given characters into a `json`
+///
+/// # Example
+///
+/// ```json
+/// [
+///   {
+///     "name": "SHAMAN",
+///     "lines": 2,
+///     "words": 11,
+///     "first_page": 1,
+///     "last_page": 2,
+///     "scenes": [0, 3],
+///     "locations": ["Snowy Landscape", "Hut"],
+///     "variants": ["Shaman", "SHAMAN"]
+///   }
+/// ]
+/// ```
+pub fn format_json<W: Write>(characters: &Vec<Character>, output: &mut W) -> io::Result<()> {
+    try!(serde_json::to_writer_pretty(&mut *output, characters));
+    try!(writeln!(output));
+    output.flush()
+}
+
+/// Serialize the given characters into a `csv`
+///
+/// The lists of scenes, locations and variants are separated by `;`.
+///
+/// # Example
+///
+/// ```text
+/// name,lines,words,first_page,last_page,scenes,locations,variants
+/// SHAMAN,2,11,1,2,0;3,Snowy Landscape;Hut,Shaman;SHAMAN
+/// ```
+pub fn format_csv<W: Write>(characters: &Vec<Character>, output: &mut W) -> io::Result<()> {
+    try!(writeln!(output, "name,lines,words,first_page,last_page,scenes,locations,variants"));
+
+    for character in characters.iter() {
+        let scenes: Vec<String> = character.scenes.iter().map(|s| s.to_string()).collect();
+        try!(writeln!(output, "{},{},{},{},{},{},{},{}",
+                      csv_field(&character.name),
+                      character.lines,
+                      character.words,
+                      character.first_page,
+                      character.last_page,
+                      scenes.join(";"),
+                      csv_field(&character.locations.join(";")),
+                      csv_field(&character.variants.join(";"))));
+    }
+
+    output.flush()
+}
+
+
+use ::{Dialog, DialogPart, ScenePart, Script};
+use regex::Regex;
+use serde_json;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+lazy_static! {
+    static ref EXTENSIONS: Regex = Regex::new(r"\([^()]*\)").unwrap();
+    static ref VOICE: Regex = Regex::new(r"^(.+?)(?:'S|’S|'|’) VOICE$").unwrap();
+}
+
+fn count_words(dialog: &Vec<DialogPart>) -> u32 {
+    dialog.iter().map(|part| match part {
+        &DialogPart::Dialog(ref text, _, _) => text.split_whitespace().count() as u32,
+        &DialogPart::Direction(_, _, _) => 0,
+    }).sum()
+}
+
+/// Quotes the given field if it contains special characters.
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", field.replace("\"", "\"\""))
+    } else {
+        field.to_string()
+    }
+}
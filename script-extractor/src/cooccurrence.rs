@@ -0,0 +1,269 @@
+//! Computing how often the characters of a `Script` appear together.
+//!
+//! Two characters appear together if both of them speak in the same `Scene`
+//! or `Location`, counting every character of a cue like `WOODY AND BUZZ`
+//! but no groups like `CROWD`. The resulting matrix can be used for chord
+//! diagrams or be exported as a graph.
+
+/// The parts of a `Script` in which characters are considered to appear
+/// together.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Granularity {
+    Scene,
+    Location,
+}
+
+/// How the appearances of two characters are counted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Weighting {
+    /// The number of scenes or locations in which both characters speak.
+    ///
+    /// The diagonal contains the number of scenes or locations in which a
+    /// character speaks at all.
+    SharedScenes,
+    /// The number of times one character speaks directly after the other.
+    DialogExchanges,
+}
+
+/// A symmetric matrix of how often two characters appear together.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Cooccurrences {
+    /// The normalized names of the characters, sorted alphabetically
+    pub characters: Vec<String>,
+    /// The weights, indexed in the same order as `characters`
+    pub matrix: Vec<Vec<u32>>,
+}
+
+/// Computes the co-occurrences of all characters in the given `Script`.
+pub fn compute_cooccurrences(script: &Script, granularity: Granularity, weighting: Weighting)
+    -> Cooccurrences {
+    // the characters of every dialog of a scene or location in the order
+    // they speak
+    let mut units: Vec<Vec<Vec<String>>> = Vec::new();
+    for scene in script.scenes.iter() {
+        if granularity == Granularity::Scene {
+            units.push(Vec::new());
+        }
+        for location in scene.iter() {
+            if granularity == Granularity::Location {
+                units.push(Vec::new());
+            }
+
+            let unit = units.last_mut().unwrap();
+            for dialog in location.parts.iter().flat_map(|part| part.dialogs()) {
+                let names = dialog_characters(dialog);
+                if names.len() > 0 {
+                    unit.push(names);
+                }
+            }
+        }
+    }
+
+    let names: BTreeSet<&String> = units.iter().flat_map(|unit| unit.iter()).flat_map(|names| names.iter()).collect();
+    let characters: Vec<String> = names.into_iter().cloned().collect();
+    let index = |name: &String| characters.binary_search(name).unwrap();
+
+    let mut matrix = vec![vec![0; characters.len()]; characters.len()];
+    for unit in units.iter() {
+        match weighting {
+            Weighting::SharedScenes => {
+                let speakers: BTreeSet<usize> = unit.iter().flat_map(|names| names.iter()).map(|name| index(name)).collect();
+                for &a in speakers.iter() {
+                    for &b in speakers.iter() {
+                        matrix[a][b] += 1;
+                    }
+                }
+            }
+            Weighting::DialogExchanges => {
+                // everyone speaking in a dialog replies to everyone of the previous one
+                for pair in unit.windows(2) {
+                    for a in pair[0].iter().map(|name| index(name)) {
+                        for b in pair[1].iter().map(|name| index(name)) {
+                            if a != b {
+                                matrix[a][b] += 1;
+                                matrix[b][a] += 1;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Cooccurrences {
+        characters: characters,
+        matrix: matrix,
+    }
+}
+
+/// Serialize the given co-occurrences into a `json` matrix
+///
+/// # Example
+///
+/// ```json
+/// {
+///   "characters": ["SHAMAN", "SINTEL"],
+///   "matrix": [
+///     [4, 2],
+///     [2, 3]
+///   ]
+/// }
+/// ```
+pub fn format_json<W: Write>(cooccurrences: &Cooccurrences, output: &mut W) -> io::Result<()> {
+    try!(serde_json::to_writer_pretty(&mut *output, cooccurrences));
+    try!(writeln!(output));
+    output.flush()
+}
+
+/// Serialize the given co-occurrences into a `GraphML` graph
+///
+/// Each character becomes a node and each pair of characters appearing
+/// together an undirected edge with the co-occurrence as weight.
+///
+/// # Example
+///
+/// ```xml
+/// <?xml version="1.0" encoding="UTF-8"?>
+/// <graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+///   <key id="label" for="node" attr.name="label" attr.type="string" />
+///   <key id="weight" for="edge" attr.name="weight" attr.type="int" />
+///   <graph id="cooccurrences" edgedefault="undirected">
+///     <node id="n0">
+///       <data key="label">SHAMAN</data>
+///     </node>
+///     <!-- (...) -->
+///     <edge source="n0" target="n1">
+///       <data key="weight">2</data>
+///     </edge>
+///   </graph>
+/// </graphml>
+/// ```
+pub fn format_graphml<W: Write>(cooccurrences: &Cooccurrences, output: &mut W) -> XmlResult<()> {
+    let mut writer = EmitterConfig::new().perform_indent(true).create_writer(output);
+
+    try!(writer.write(XmlEvent::start_element("graphml")
+                               .default_ns("http://graphml.graphdrawing.org/xmlns")));
+    try!(writer.write(XmlEvent::start_element("key")
+                               .attr("id", "label")
+                               .attr("for", "node")
+                               .attr("attr.name", "label")
+                               .attr("attr.type", "string")));
+    try!(writer.write(XmlEvent::end_element()));
+    try!(writer.write(XmlEvent::start_element("key")
+                               .attr("id", "weight")
+                               .attr("for", "edge")
+                               .attr("attr.name", "weight")
+                               .attr("attr.type", "int")));
+    try!(writer.write(XmlEvent::end_element()));
+    try!(writer.write(XmlEvent::start_element("graph")
+                               .attr("id", "cooccurrences")
+                               .attr("edgedefault", "undirected")));
+
+    for (i, character) in cooccurrences.characters.iter().enumerate() {
+        let id = format!("n{}", i);
+        try!(writer.write(XmlEvent::start_element("node").attr("id", &id)));
+        try!(format_graphml_data("label", character, &mut writer));
+        try!(writer.write(XmlEvent::end_element()));
+    }
+
+    for (a, b, weight) in edges(cooccurrences) {
+        let (source, target) = (format!("n{}", a), format!("n{}", b));
+        try!(writer.write(XmlEvent::start_element("edge")
+                                   .attr("source", &source)
+                                   .attr("target", &target)));
+        try!(format_graphml_data("weight", &weight.to_string(), &mut writer));
+        try!(writer.write(XmlEvent::end_element()));
+    }
+
+    try!(writer.write(XmlEvent::end_element()));
+    try!(writer.write(XmlEvent::end_element()));
+
+    Ok(())
+}
+
+/// Serialize the given co-occurrences into a `GEXF` graph
+///
+/// Each character becomes a node and each pair of characters appearing
+/// together an undirected edge with the co-occurrence as weight.
+///
+/// # Example
+///
+/// ```xml
+/// <?xml version="1.0" encoding="UTF-8"?>
+/// <gexf xmlns="http://www.gexf.net/1.2draft" version="1.2">
+///   <graph defaultedgetype="undirected">
+///     <nodes>
+///       <node id="0" label="SHAMAN" />
+///       <!-- (...) -->
+///     </nodes>
+///     <edges>
+///       <edge id="0" source="0" target="1" weight="2" />
+///     </edges>
+///   </graph>
+/// </gexf>
+/// ```
+pub fn format_gexf<W: Write>(cooccurrences: &Cooccurrences, output: &mut W) -> XmlResult<()> {
+    let mut writer = EmitterConfig::new().perform_indent(true).create_writer(output);
+
+    try!(writer.write(XmlEvent::start_element("gexf")
+                               .default_ns("http://www.gexf.net/1.2draft")
+                               .attr("version", "1.2")));
+    try!(writer.write(XmlEvent::start_element("graph").attr("defaultedgetype", "undirected")));
+
+    try!(writer.write(XmlEvent::start_element("nodes")));
+    for (i, character) in cooccurrences.characters.iter().enumerate() {
+        let id = i.to_string();
+        try!(writer.write(XmlEvent::start_element("node")
+                                   .attr("id", &id)
+                                   .attr("label", character)));
+        try!(writer.write(XmlEvent::end_element()));
+    }
+    try!(writer.write(XmlEvent::end_element()));
+
+    try!(writer.write(XmlEvent::start_element("edges")));
+    for (i, (a, b, weight)) in edges(cooccurrences).into_iter().enumerate() {
+        let (id, source, target, weight) = (i.to_string(), a.to_string(),
+                                            b.to_string(), weight.to_string());
+        try!(writer.write(XmlEvent::start_element("edge")
+                                   .attr("id", &id)
+                                   .attr("source", &source)
+                                   .attr("target", &target)
+                                   .attr("weight", &weight)));
+        try!(writer.write(XmlEvent::end_element()));
+    }
+    try!(writer.write(XmlEvent::end_element()));
+
+    try!(writer.write(XmlEvent::end_element()));
+    try!(writer.write(XmlEvent::end_element()));
+
+    Ok(())
+}
+
+
+use ::Script;
+use characters::dialog_characters;
+use serde_json;
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use xml::{EventWriter, EmitterConfig};
+use xml::writer::Result as XmlResult;
+use xml::writer::XmlEvent;
+
+/// Lists the edges of the upper triangle of the matrix with a weight.
+fn edges(cooccurrences: &Cooccurrences) -> Vec<(usize, usize, u32)> {
+    let mut edges = Vec::new();
+    for (a, row) in cooccurrences.matrix.iter().enumerate() {
+        for (b, &weight) in row.iter().enumerate().skip(a + 1) {
+            if weight > 0 {
+                edges.push((a, b, weight));
+            }
+        }
+    }
+    edges
+}
+
+fn format_graphml_data<W: Write>(key: &str, value: &str, writer: &mut EventWriter<W>) -> XmlResult<()> {
+    try!(writer.write(XmlEvent::start_element("data").attr("key", key)));
+    try!(writer.write(XmlEvent::characters(value)));
+    writer.write(XmlEvent::end_element())
+}
@@ -1,14 +1,75 @@
 //! Library to parse movie scripts and output them in a structured format
 
+#[macro_use]
+extern crate lazy_static;
 extern crate regex;
-extern crate rustc_serialize;
+extern crate rmp_serde;
+extern crate ron;
+extern crate serde;
+extern crate serde_cbor;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate serde_yaml;
+extern crate toml;
 extern crate xml;
 
+pub mod aliases;
+pub mod characters;
+pub mod cooccurrence;
+pub mod narrative;
 pub mod parse;
 pub mod serialize;
 
-/// A `Script` consists of a list of `Scene`s.
-pub type Script = Vec<Scene>;
+use std::cmp;
+use std::str::FromStr;
+
+/// A `Script` consists of its `ScriptMetadata` and a list of `Scene`s.
+///
+/// All types of the `Script` can be serialized and deserialized with serde,
+/// using the structure described in `serialize::json::format_script`.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Script {
+    /// The information from the title page of the script
+    #[serde(default)]
+    pub metadata: ScriptMetadata,
+    pub scenes: Vec<Scene>,
+}
+
+/// The information given on the title page of a script.
+///
+/// All fields are empty if the script has no title page.
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScriptMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The authors in the order of the title page, like the authors of the
+    /// screenplay followed by the authors of the story
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub credits: Vec<Credit>,
+    /// The work the script is based on, like `Based on the novel by ...`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub draft_date: Option<String>,
+    /// The revision of the script, like `Blue Revision`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// The contact block, usually the address of the agency or the writer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact: Option<String>,
+}
+
+/// Authors of a script credited together for their work, like `Story by`.
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Credit {
+    /// The line introducing the authors, like `Written by`, if there is one
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub credit: String,
+    #[serde(default)]
+    pub authors: Vec<String>,
+}
 
 /// A `Scene` consists of a list of `Location`s.
 ///
@@ -18,51 +79,262 @@ pub type Script = Vec<Scene>;
 pub type Scene = Vec<Location>;
 
 /// Represents a location in which part of a `Scene` takes place.
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct Location {
-    /// The kind of the location (like internal)
-    pub kind: LocationType,
     /// The name of the location
+    #[serde(rename = "place", default)]
     pub name: String,
-    /// The `Dialog` and `Direction` which take place in this location
+    /// The kind of the location (like internal)
+    #[serde(rename = "type", default, skip_serializing_if = "is_undefined")]
+    pub kind: LocationType,
+    /// The scene number of the heading, like `42` or `42A`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number: Option<String>,
+    /// The main place of the name, like `HOUSE` in `HOUSE - BEDROOM - NIGHT`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub primary_place: String,
+    /// The places within the primary place, like `BEDROOM`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sub_locations: Vec<String>,
+    /// The time of day at the end of the name, like `NIGHT`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_of_day: Option<TimeOfDay>,
+    /// The `Dialog`, `Direction` and `Transition` which take place in this
+    /// location
     pub parts: Vec<ScenePart>,
 }
 
 /// Represents the types of locations often used in scripts.
-#[derive(Clone, Debug)]
+///
+/// The types are serialized as their string representation.
+#[derive(Clone, Debug, PartialEq)]
 pub enum LocationType {
     Undefined,
     Internal,
     External,
     InternalExternal,
+    /// An establishing shot, usually of the outside of a building
+    Establishing,
+}
+
+/// The times of day which can end a scene heading.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeOfDay {
+    Day,
+    Night,
+    Morning,
+    Evening,
+    Dusk,
+    Dawn,
+    /// The scene follows the previous one without a break in time
+    Continuous,
+    Later,
+    MomentsLater,
+    /// The scene happens at the same time as the previous one
+    Same,
+    /// Any other time, like `LATE AFTERNOON`
+    Other(String),
 }
 
-/// A `Scene` consists of `Direction`s and `Dialog`s.
+/// A `Scene` consists of `Direction`s, `Dialog`s, `DualDialog`s and
+/// `Transition`s.
 ///
-/// Each `ScenePart` carries the page number from which it was extracted
-/// originally.
+/// Each `ScenePart` carries the pages on which it starts and ends in the
+/// original script, together with the vertical positions of its first and
+/// last line if these are known. `Direction`s and `Dialog`s also carry
+/// whether they were marked as revised, usually with an asterisk in the
+/// margin. Parts extracted from pdfs carry the `SourceSpan`s of their lines
+/// and `Direction`s the `Emphasis` of their text.
 #[derive(Debug, Clone)]
 pub enum ScenePart {
     Direction {
         direction: String,
         page: u32,
+        /// The page on which the direction ends
+        end_page: u32,
+        /// The vertical position of the first line on `page`
+        top: Option<i32>,
+        /// The vertical position of the last line on `end_page`
+        end_top: Option<i32>,
+        revised: bool,
+        /// The regions of the pages the direction was extracted from
+        source: Vec<SourceSpan>,
+        /// The emphasized ranges of `direction`
+        emphasis: Vec<Emphasis>,
     },
-    Dialog {
-        speaker: String,
-        dialog: Vec<DialogPart>,
+    Dialog(Dialog),
+    /// Two `Dialog`s spoken at the same time, which are printed side by side
+    DualDialog {
+        /// The `Dialog` of the left column
+        left: Dialog,
+        /// The `Dialog` of the right column
+        right: Dialog,
+    },
+    /// A transition between two shots or scenes, like `CUT TO:`
+    Transition {
+        kind: TransitionKind,
+        /// The text of the transition as written in the script
+        text: String,
         page: u32,
-    }
+        end_page: u32,
+        top: Option<i32>,
+        end_top: Option<i32>,
+        source: Vec<SourceSpan>,
+    },
+}
+
+/// What a speaker says in a `Scene`, together with the inline directions.
+#[derive(Debug, Clone)]
+pub struct Dialog {
+    /// The character cue without its extensions, which names the speaking
+    /// characters (see `speakers`)
+    pub speaker: String,
+    /// The extensions following the speaker's name, like `(V.O.)`
+    pub mode: Vec<DialogMode>,
+    pub dialog: Vec<DialogPart>,
+    pub page: u32,
+    /// The page on which the dialog ends, if it continues across a page
+    /// break
+    pub end_page: u32,
+    /// The vertical position of the speaker's cue on `page`
+    pub top: Option<i32>,
+    /// The vertical position of the last line on `end_page`
+    pub end_top: Option<i32>,
+    pub revised: bool,
+    /// The regions of the pages the dialog was extracted from, including the
+    /// speaker's cue
+    pub source: Vec<SourceSpan>,
+}
+
+/// The region of a pdf page from which a part of the script was extracted.
+///
+/// A part continuing across a page break has one `SourceSpan` per page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceSpan {
+    pub page: u32,
+    /// The bounding box of the lines on the page
+    pub top: i32,
+    pub left: i32,
+    pub bottom: i32,
+    pub right: i32,
+    /// The indices of the `<text>` elements of the lines, counted from the
+    /// start of the document
+    pub lines: Vec<usize>,
+}
+
+/// The kinds of transitions commonly used in scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionKind {
+    /// `CUT TO:`
+    Cut,
+    /// `SMASH CUT TO:`
+    SmashCut,
+    /// `MATCH CUT TO:`
+    MatchCut,
+    /// `DISSOLVE TO:`
+    Dissolve,
+    /// `FADE IN:`
+    FadeIn,
+    /// `FADE OUT.` or `FADE TO BLACK.`
+    FadeOut,
+    /// `INTERCUT WITH:`, which alternates between two locations
+    Intercut,
+    /// Any other transition ending with `TO:`, like `WIPE TO:`
+    Other,
+}
+
+/// How `filter_script` matches the pages of the `ScenePart`s with a range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageFilter {
+    /// Keeps the parts with at least one page in the range
+    Overlaps,
+    /// Keeps only the parts which start and end in the range
+    Within,
+}
+
+/// An emphasized range of a text, like a bold word in a direction.
+///
+/// The range is given in characters, not in bytes, and excludes the end.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Emphasis {
+    pub style: EmphasisStyle,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The styles of `Emphasis` found in scripts.
+///
+/// Underlined text, like underlined transitions or headings, cannot be kept:
+/// pdfs draw underlines as lines separate from the text, which `pdftohtml`
+/// does not output, and neither its fonts nor its inline elements (only
+/// `<b>` and `<i>`) mark them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmphasisStyle {
+    Bold,
+    Italic,
+}
+
+/// The extensions which can follow a speaker's name in a `Dialog`.
+///
+/// A speaker can carry several extensions, like `WOODY (V.O.) (CONT'D)`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DialogMode {
+    /// `(V.O.)`: the speaker is not part of the scene
+    VoiceOver,
+    /// `(O.S.)`: the speaker is in the scene, but not visible
+    OffScreen,
+    /// `(O.C.)`: the speaker is in the scene, but not in the frame
+    OffCamera,
+    /// `(CONT'D)`: the speaker continues after an interruption
+    Continued,
+    /// Any other extension, like `(into phone)`
+    Other(String),
+}
+
+/// One of the speakers of a `Dialog`.
+///
+/// A character cue can name several characters speaking together, or a
+/// group of unnamed characters like `CROWD`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Speaker {
+    /// A single character, like `WOODY`
+    Character(String),
+    /// A group of characters, like `EVERYONE` or `ALIENS (IN UNISON)`
+    Group(String),
 }
 
 /// The different parts of a `Dialog`.
 ///
 /// A `DialogPart` can have inline `Direction`s in between normal `Dialog`.
+/// Both carry the `SourceSpan`s of their lines and the `Emphasis` of their
+/// text, if they were extracted from a pdf.
 #[derive(Debug, Clone)]
 pub enum DialogPart {
     /// What a speaker says
-    Dialog(String),
+    Dialog(String, Vec<SourceSpan>, Vec<Emphasis>),
     /// How or to whom the speaker says it
-    Direction(String),
+    Direction(String, Vec<SourceSpan>, Vec<Emphasis>),
+}
+
+/// Parses the string representation of a `LocationType`.
+///
+/// This is the inverse of the conversion into a string, so the empty string
+/// becomes `Undefined`.
+impl FromStr for LocationType {
+    type Err = ();
+
+    fn from_str(kind: &str) -> Result<LocationType, ()> {
+        match kind {
+            "" => Ok(LocationType::Undefined),
+            "internal" => Ok(LocationType::Internal),
+            "external" => Ok(LocationType::External),
+            "internal,external" => Ok(LocationType::InternalExternal),
+            "establishing" => Ok(LocationType::Establishing),
+            _ => Err(()),
+        }
+    }
 }
 
 /// The default for `LocationType` is `Undefined`.
@@ -80,6 +352,426 @@ impl Into<&'static str> for LocationType {
             LocationType::Internal => "internal",
             LocationType::External => "external",
             LocationType::InternalExternal => "internal,external",
+            LocationType::Establishing => "establishing",
+        }
+    }
+}
+
+impl ScriptMetadata {
+    /// Checks if no information of the title page is known.
+    pub fn is_empty(&self) -> bool {
+        *self == Default::default()
+    }
+}
+
+impl ScenePart {
+    /// Returns the page on which the `ScenePart` starts.
+    pub fn page(&self) -> u32 {
+        match self {
+            &ScenePart::Direction { page, .. } => page,
+            &ScenePart::Dialog(ref dialog) => dialog.page,
+            &ScenePart::DualDialog { ref left, ref right } => cmp::min(left.page, right.page),
+            &ScenePart::Transition { page, .. } => page,
+        }
+    }
+
+    /// Returns the page on which the `ScenePart` ends.
+    pub fn end_page(&self) -> u32 {
+        match self {
+            &ScenePart::Direction { end_page, .. } => end_page,
+            &ScenePart::Dialog(ref dialog) => dialog.end_page,
+            &ScenePart::DualDialog { ref left, ref right } => cmp::max(left.end_page, right.end_page),
+            &ScenePart::Transition { end_page, .. } => end_page,
+        }
+    }
+
+    /// Returns the `SourceSpan`s of the `ScenePart`.
+    ///
+    /// A `DualDialog` has no `SourceSpan`s of its own, they are kept by its
+    /// two `Dialog`s.
+    pub fn source(&self) -> &[SourceSpan] {
+        match self {
+            &ScenePart::Direction { ref source, .. } => source,
+            &ScenePart::Dialog(ref dialog) => &dialog.source,
+            &ScenePart::DualDialog { .. } => &[],
+            &ScenePart::Transition { ref source, .. } => source,
+        }
+    }
+
+    /// Checks if the pages of the `ScenePart` match the inclusive range
+    /// using the given `PageFilter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use script_extractor::{PageFilter, ScenePart};
+    /// let direction = ScenePart::Direction {
+    ///     direction: "She drinks.".to_string(),
+    ///     page: 4,
+    ///     end_page: 5,
+    ///     top: None,
+    ///     end_top: None,
+    ///     revised: false,
+    ///     source: Vec::new(),
+    ///     emphasis: Vec::new(),
+    /// };
+    ///
+    /// assert!(direction.matches_pages((5, 5), PageFilter::Overlaps));
+    /// assert!(!direction.matches_pages((5, 5), PageFilter::Within));
+    /// assert!(direction.matches_pages((4, 5), PageFilter::Within));
+    /// ```
+    pub fn matches_pages(&self, page_range: (u32, u32), filter: PageFilter) -> bool {
+        let (lower, upper) = page_range;
+        let (page, end_page) = (self.page(), self.end_page());
+
+        match filter {
+            PageFilter::Overlaps => page <= upper && lower <= end_page,
+            PageFilter::Within => lower <= page && end_page <= upper,
+        }
+    }
+
+    /// Returns the `Dialog`s of the `ScenePart`.
+    ///
+    /// This is the `Dialog` of a `ScenePart::Dialog`, both `Dialog`s of a
+    /// `DualDialog` and nothing for all other parts.
+    pub fn dialogs(&self) -> Vec<&Dialog> {
+        match self {
+            &ScenePart::Dialog(ref dialog) => vec![dialog],
+            &ScenePart::DualDialog { ref left, ref right } => vec![left, right],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Dialog {
+    /// Returns the characters speaking together, like `WOODY` and `BUZZ` for
+    /// the speaker `WOODY AND BUZZ`.
+    ///
+    /// They are always split from `speaker` and `mode` (see
+    /// `parse::split_speakers`), so changing the cue changes the speakers.
+    pub fn speakers(&self) -> Vec<Speaker> {
+        parse::split_speakers(&self.speaker, &self.mode)
+    }
+}
+
+impl SourceSpan {
+    /// Creates a `SourceSpan` of a single line.
+    pub fn new(page: u32, top: i32, left: i32, bottom: i32, right: i32, line: usize) -> SourceSpan {
+        SourceSpan {
+            page: page,
+            top: top,
+            left: left,
+            bottom: bottom,
+            right: right,
+            lines: vec![line],
+        }
+    }
+
+    /// Adds a line to the `SourceSpans`, extending the last span if the line
+    /// is on the same page.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use script_extractor::SourceSpan;
+    /// let mut spans = Vec::new();
+    /// SourceSpan::extend(&mut spans, SourceSpan::new(1, 205, 180, 220, 480, 4));
+    /// SourceSpan::extend(&mut spans, SourceSpan::new(1, 220, 180, 235, 420, 5));
+    /// SourceSpan::extend(&mut spans, SourceSpan::new(2, 100, 180, 115, 400, 9));
+    ///
+    /// assert_eq!(spans.len(), 2);
+    /// assert_eq!((spans[0].top, spans[0].bottom, spans[0].right), (205, 235, 480));
+    /// assert_eq!(spans[0].lines, vec![4, 5]);
+    /// ```
+    pub fn extend(spans: &mut Vec<SourceSpan>, line: SourceSpan) {
+        if let Some(span) = spans.last_mut() {
+            if span.page == line.page {
+                span.top = cmp::min(span.top, line.top);
+                span.left = cmp::min(span.left, line.left);
+                span.bottom = cmp::max(span.bottom, line.bottom);
+                span.right = cmp::max(span.right, line.right);
+                span.lines.extend(line.lines);
+                return;
+            }
+        }
+
+        spans.push(line);
+    }
+}
+
+impl Emphasis {
+    /// Sorts the `Emphasis` of a text and merges the overlapping or adjacent
+    /// ranges of the same style.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use script_extractor::{Emphasis, EmphasisStyle};
+    /// let mut emphasis = vec![Emphasis { style: EmphasisStyle::Bold, start: 6, end: 10 },
+    ///                         Emphasis { style: EmphasisStyle::Italic, start: 2, end: 4 },
+    ///                         Emphasis { style: EmphasisStyle::Bold, start: 0, end: 6 }];
+    /// Emphasis::normalize(&mut emphasis);
+    ///
+    /// assert_eq!(emphasis, vec![Emphasis { style: EmphasisStyle::Bold, start: 0, end: 10 },
+    ///                           Emphasis { style: EmphasisStyle::Italic, start: 2, end: 4 }]);
+    /// ```
+    pub fn normalize(emphasis: &mut Vec<Emphasis>) {
+        emphasis.retain(|emphasis| emphasis.start < emphasis.end);
+        emphasis.sort_by(|a, b| (a.style, a.start, a.end).cmp(&(b.style, b.start, b.end)));
+
+        let mut merged: Vec<Emphasis> = Vec::new();
+        for range in emphasis.drain(..) {
+            if let Some(last) = merged.last_mut() {
+                if last.style == range.style && range.start <= last.end {
+                    last.end = cmp::max(last.end, range.end);
+                    continue;
+                }
+            }
+            merged.push(range);
+        }
+
+        merged.sort_by(|a, b| (a.start, a.style).cmp(&(b.start, b.style)));
+        *emphasis = merged;
+    }
+}
+
+impl EmphasisStyle {
+    /// Converts the `EmphasisStyle` into a string representation.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            &EmphasisStyle::Bold => "bold",
+            &EmphasisStyle::Italic => "italic",
+        }
+    }
+}
+
+/// Parses the string representation of an `EmphasisStyle`.
+///
+/// This is the inverse of `EmphasisStyle::as_str`.
+impl FromStr for EmphasisStyle {
+    type Err = ();
+
+    fn from_str(style: &str) -> Result<EmphasisStyle, ()> {
+        match style {
+            "bold" => Ok(EmphasisStyle::Bold),
+            "italic" => Ok(EmphasisStyle::Italic),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Speaker {
+    /// Returns the name of the character or group.
+    pub fn name(&self) -> &str {
+        match self {
+            &Speaker::Character(ref name) => name,
+            &Speaker::Group(ref name) => name,
+        }
+    }
+
+    /// Checks if the `Speaker` is a group of characters.
+    pub fn is_group(&self) -> bool {
+        match self {
+            &Speaker::Character(_) => false,
+            &Speaker::Group(_) => true,
+        }
+    }
+}
+
+impl DialogMode {
+    /// Parses the text of an extension (without the parentheses).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use script_extractor::DialogMode;
+    /// assert_eq!(DialogMode::from_extension("V.O."), DialogMode::VoiceOver);
+    /// assert_eq!(DialogMode::from_extension("cont’d"), DialogMode::Continued);
+    /// assert_eq!(DialogMode::from_extension("into phone"),
+    ///            DialogMode::Other("into phone".to_string()));
+    /// ```
+    pub fn from_extension(extension: &str) -> DialogMode {
+        let normalized: String = extension.chars()
+                                          .filter(|c| c.is_alphanumeric())
+                                          .flat_map(|c| c.to_uppercase())
+                                          .collect();
+
+        match normalized.as_ref() {
+            "VO" => DialogMode::VoiceOver,
+            "OS" => DialogMode::OffScreen,
+            "OC" => DialogMode::OffCamera,
+            "CONTD" | "CONT" | "CONTINUED" | "CONTINUING" => DialogMode::Continued,
+            _ => DialogMode::Other(extension.trim().to_string()),
+        }
+    }
+
+    /// Converts the `DialogMode` into a string representation.
+    ///
+    /// `Other` extensions are returned verbatim.
+    pub fn as_str(&self) -> &str {
+        match self {
+            &DialogMode::VoiceOver => "VO",
+            &DialogMode::OffScreen => "OS",
+            &DialogMode::OffCamera => "OC",
+            &DialogMode::Continued => "CONT'D",
+            &DialogMode::Other(ref extension) => extension,
+        }
+    }
+}
+
+impl TimeOfDay {
+    /// Parses the time of day at the end of a scene heading.
+    ///
+    /// Returns `None` if the text is no time of day. Texts which are not one
+    /// of the common times, but contain a word like `AFTERNOON` or `SUNSET`,
+    /// become `Other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use script_extractor::TimeOfDay;
+    /// assert_eq!(TimeOfDay::from_text("Night"), Some(TimeOfDay::Night));
+    /// assert_eq!(TimeOfDay::from_text("MOMENTS LATER"), Some(TimeOfDay::MomentsLater));
+    /// assert_eq!(TimeOfDay::from_text("LATE AFTERNOON"),
+    ///            Some(TimeOfDay::Other("LATE AFTERNOON".to_string())));
+    /// assert_eq!(TimeOfDay::from_text("BEDROOM"), None);
+    /// ```
+    pub fn from_text(text: &str) -> Option<TimeOfDay> {
+        let normalized = text.trim_end_matches('.')
+                             .split_whitespace()
+                             .collect::<Vec<_>>()
+                             .join(" ")
+                             .to_uppercase();
+
+        let time_of_day = match normalized.as_ref() {
+            "DAY" => TimeOfDay::Day,
+            "NIGHT" => TimeOfDay::Night,
+            "MORNING" => TimeOfDay::Morning,
+            "EVENING" => TimeOfDay::Evening,
+            "DUSK" => TimeOfDay::Dusk,
+            "DAWN" => TimeOfDay::Dawn,
+            "CONTINUOUS" | "CONT" => TimeOfDay::Continuous,
+            "LATER" => TimeOfDay::Later,
+            "MOMENTS LATER" | "MOMENT LATER" | "A MOMENT LATER" => TimeOfDay::MomentsLater,
+            "SAME" | "SAME TIME" => TimeOfDay::Same,
+            _ => {
+                let time_words = ["DAY", "NIGHT", "MORNING", "EVENING", "AFTERNOON", "NOON",
+                                  "MIDNIGHT", "DUSK", "DAWN", "SUNRISE", "SUNSET", "LATER"];
+                if normalized.split(' ').any(|word| time_words.contains(&word)) {
+                    TimeOfDay::Other(text.trim().to_string())
+                } else {
+                    return None;
+                }
+            }
+        };
+
+        Some(time_of_day)
+    }
+
+    /// Converts the `TimeOfDay` into a string representation.
+    ///
+    /// `Other` times are returned verbatim.
+    pub fn as_str(&self) -> &str {
+        match self {
+            &TimeOfDay::Day => "DAY",
+            &TimeOfDay::Night => "NIGHT",
+            &TimeOfDay::Morning => "MORNING",
+            &TimeOfDay::Evening => "EVENING",
+            &TimeOfDay::Dusk => "DUSK",
+            &TimeOfDay::Dawn => "DAWN",
+            &TimeOfDay::Continuous => "CONTINUOUS",
+            &TimeOfDay::Later => "LATER",
+            &TimeOfDay::MomentsLater => "MOMENTS LATER",
+            &TimeOfDay::Same => "SAME",
+            &TimeOfDay::Other(ref time) => time,
+        }
+    }
+}
+
+impl TransitionKind {
+    /// Recognizes the transition in the given line of a script.
+    ///
+    /// Transitions have to be written in uppercase. Returns `None` if the
+    /// line is no transition.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use script_extractor::TransitionKind;
+    /// assert_eq!(TransitionKind::from_text("CUT TO:"), Some(TransitionKind::Cut));
+    /// assert_eq!(TransitionKind::from_text("SMASH CUT TO:"), Some(TransitionKind::SmashCut));
+    /// assert_eq!(TransitionKind::from_text("FADE OUT."), Some(TransitionKind::FadeOut));
+    /// assert_eq!(TransitionKind::from_text("INTERCUT WITH:"), Some(TransitionKind::Intercut));
+    /// assert_eq!(TransitionKind::from_text("WIPE TO:"), Some(TransitionKind::Other));
+    /// assert_eq!(TransitionKind::from_text("Cut to the chase."), None);
+    /// assert_eq!(TransitionKind::from_text("FADE"), None);
+    /// ```
+    pub fn from_text(text: &str) -> Option<TransitionKind> {
+        let text = text.trim();
+        let is_uppercase = text.chars().any(|c| c.is_alphabetic()) && text.to_uppercase() == text;
+        if !is_uppercase {
+            return None;
+        }
+
+        let normalized = text.trim_end_matches(|c| c == ':' || c == '.')
+                             .split_whitespace()
+                             .collect::<Vec<_>>()
+                             .join(" ");
+
+        let phrases = [("CUT TO", TransitionKind::Cut),
+                       ("SMASH CUT", TransitionKind::SmashCut),
+                       ("MATCH CUT", TransitionKind::MatchCut),
+                       ("DISSOLVE", TransitionKind::Dissolve),
+                       ("FADE IN", TransitionKind::FadeIn),
+                       ("FADE OUT", TransitionKind::FadeOut),
+                       ("FADE TO BLACK", TransitionKind::FadeOut),
+                       ("INTERCUT", TransitionKind::Intercut)];
+
+        for &(phrase, kind) in phrases.iter() {
+            if normalized == phrase || normalized.starts_with(&format!("{} ", phrase)) {
+                return Some(kind);
+            }
+        }
+
+        if text.ends_with(" TO:") {
+            Some(TransitionKind::Other)
+        } else {
+            None
+        }
+    }
+
+    /// Converts the `TransitionKind` into a string representation.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            &TransitionKind::Cut => "cut",
+            &TransitionKind::SmashCut => "smash_cut",
+            &TransitionKind::MatchCut => "match_cut",
+            &TransitionKind::Dissolve => "dissolve",
+            &TransitionKind::FadeIn => "fade_in",
+            &TransitionKind::FadeOut => "fade_out",
+            &TransitionKind::Intercut => "intercut",
+            &TransitionKind::Other => "other",
+        }
+    }
+}
+
+/// Parses the string representation of a `TransitionKind`.
+///
+/// This is the inverse of `TransitionKind::as_str`.
+impl FromStr for TransitionKind {
+    type Err = ();
+
+    fn from_str(kind: &str) -> Result<TransitionKind, ()> {
+        match kind {
+            "cut" => Ok(TransitionKind::Cut),
+            "smash_cut" => Ok(TransitionKind::SmashCut),
+            "match_cut" => Ok(TransitionKind::MatchCut),
+            "dissolve" => Ok(TransitionKind::Dissolve),
+            "fade_in" => Ok(TransitionKind::FadeIn),
+            "fade_out" => Ok(TransitionKind::FadeOut),
+            "intercut" => Ok(TransitionKind::Intercut),
+            "other" => Ok(TransitionKind::Other),
+            _ => Err(()),
         }
     }
 }
@@ -113,20 +805,15 @@ pub fn extract_range(range_string: &str) -> Option<(u32, u32)> {
 
 /// Filter the script using a range of pages.
 ///
-/// The range is inclusive and empty scenes and locations are removed.
-pub fn filter_script(script: Script, page_range: (u32, u32)) -> Script {
-    let (lower, upper) = page_range;
-
-    script.into_iter().filter_map(|scene| {
+/// The range is inclusive and the `PageFilter` decides whether parts which
+/// continue across the bounds of the range are kept. Empty scenes and
+/// locations are removed. The metadata of the script is kept.
+pub fn filter_script(script: Script, page_range: (u32, u32), filter: PageFilter) -> Script {
+    let scenes = script.scenes.into_iter().filter_map(|scene| {
         let filtered_scene: Scene = scene.into_iter().filter_map(|mut location| {
             let filtered_scene_parts: Vec<ScenePart> = location.parts.into_iter().filter_map(|scene_part| {
-                let page = match scene_part {
-                    ScenePart::Direction { page, .. } => page,
-                    ScenePart::Dialog { page, .. } => page
-                };
-
                 // filter using the given range
-                if lower <= page && page <= upper {
+                if scene_part.matches_pages(page_range, filter) {
                     Some(scene_part)
                 } else {
                     None
@@ -148,5 +835,90 @@ pub fn filter_script(script: Script, page_range: (u32, u32)) -> Script {
         } else {
             None
         }
-    }).collect()
+    }).collect();
+
+    Script {
+        metadata: script.metadata,
+        scenes: scenes,
+    }
+}
+
+/// Removes the `SourceSpan`s from all parts of the script.
+///
+/// This is used when the positions of the parts in the pdf are not requested
+/// for the output.
+pub fn strip_source_spans(mut script: Script) -> Script {
+    for location in script.scenes.iter_mut().flat_map(|scene| scene.iter_mut()) {
+        for part in location.parts.iter_mut() {
+            strip_part_source_spans(part);
+        }
+    }
+
+    script
+}
+
+/// Removes the `Emphasis` from all parts of the script.
+///
+/// This is used when the emphasis of the texts is not requested for the
+/// output.
+pub fn strip_emphasis(mut script: Script) -> Script {
+    for location in script.scenes.iter_mut().flat_map(|scene| scene.iter_mut()) {
+        for part in location.parts.iter_mut() {
+            strip_part_emphasis(part);
+        }
+    }
+
+    script
+}
+
+
+fn strip_part_source_spans(part: &mut ScenePart) {
+    match part {
+        &mut ScenePart::Direction { ref mut source, .. } => source.clear(),
+        &mut ScenePart::Dialog(ref mut dialog) => strip_dialog_source_spans(dialog),
+        &mut ScenePart::DualDialog { ref mut left, ref mut right } => {
+            strip_dialog_source_spans(left);
+            strip_dialog_source_spans(right);
+        }
+        &mut ScenePart::Transition { ref mut source, .. } => source.clear(),
+    }
+}
+
+fn strip_part_emphasis(part: &mut ScenePart) {
+    match part {
+        &mut ScenePart::Direction { ref mut emphasis, .. } => emphasis.clear(),
+        &mut ScenePart::Dialog(ref mut dialog) => strip_dialog_emphasis(dialog),
+        &mut ScenePart::DualDialog { ref mut left, ref mut right } => {
+            strip_dialog_emphasis(left);
+            strip_dialog_emphasis(right);
+        }
+        &mut ScenePart::Transition { .. } => {}
+    }
+}
+
+fn strip_dialog_source_spans(dialog: &mut Dialog) {
+    dialog.source.clear();
+    for dialog_part in dialog.dialog.iter_mut() {
+        match dialog_part {
+            &mut DialogPart::Dialog(_, ref mut source, _) => source.clear(),
+            &mut DialogPart::Direction(_, ref mut source, _) => source.clear(),
+        }
+    }
+}
+
+fn strip_dialog_emphasis(dialog: &mut Dialog) {
+    for dialog_part in dialog.dialog.iter_mut() {
+        match dialog_part {
+            &mut DialogPart::Dialog(_, _, ref mut emphasis) => emphasis.clear(),
+            &mut DialogPart::Direction(_, _, ref mut emphasis) => emphasis.clear(),
+        }
+    }
+}
+
+/// Leaves out the `type` of `Location`s without a known type.
+fn is_undefined(kind: &LocationType) -> bool {
+    match kind {
+        &LocationType::Undefined => true,
+        _ => false,
+    }
 }
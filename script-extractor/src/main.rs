@@ -1,33 +1,224 @@
 #[macro_use]
 extern crate clap;
 
-use clap::{App, Arg, ArgGroup};
+use clap::{App, Arg, ArgGroup, ArgMatches};
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Write};
 
 extern crate script_extractor;
 use script_extractor::*;
+use script_extractor::parse::ParseError;
+use script_extractor::parse::layout::{Column, LayoutProfile};
 
 fn main() {
     let args = App::new("script-extractor")
                    .version(&crate_version!())
                    .about("Parse movie scripts into a structured format")
-                   .after_help("Input has to be in the format generated by \
-                                poppler's 'pdftohtml -xml'.\n\
+                   .after_help("By default, input has to be in the format generated \
+                                by poppler's 'pdftohtml -xml'.\n\
                                 Reads from stdin if no file or '-' is specified.")
                    .arg(Arg::with_name("input-file")
-                            .help("input file in poppler's xml format")
+                            .help("input file in the format given by --input-format")
                             .index(1)
                             .validator(check_file_exists))
+                   .arg(Arg::with_name("input-format")
+                            .help("Format of the input file (default: poppler)")
+                            .short("f")
+                            .long("input-format")
+                            .takes_value(true)
+                            .possible_values(&["poppler", "fountain", "fdx", "json", "xml"]))
                    .arg(Arg::with_name("xml")
                             .long("xml")
                             .help("Output script in xml format"))
                    .arg(Arg::with_name("json")
                             .long("json")
                             .help("Output script in json format"))
+                   .arg(Arg::with_name("with-positions")
+                            .long("with-positions")
+                            .help("Include the regions of the pdf pages the parts were extracted from \
+                                   (not with fountain or fdx output)"))
+                   .arg(Arg::with_name("with-emphasis")
+                            .long("with-emphasis")
+                            .help("Include the bold and italic ranges of the texts of poppler input \
+                                   (not with fountain or fdx output)"))
+                   .arg(Arg::with_name("fountain")
+                            .long("fountain")
+                            .help("Output script in fountain format"))
+                   .arg(Arg::with_name("fdx")
+                            .long("fdx")
+                            .help("Output script in Final Draft format"))
+                   .arg(Arg::with_name("format")
+                            .long("format")
+                            .help("Output script in the given format")
+                            .takes_value(true)
+                            .possible_values(&["json", "xml", "fountain", "fdx", "yaml", "msgpack", "cbor", "ron"])
+                            .conflicts_with_all(&["characters", "cooccurrence", "narrative"]))
+                   .arg(Arg::with_name("csv")
+                            .long("csv")
+                            .help("Output in csv format (only with --characters)")
+                            .requires("characters"))
+                   .arg(Arg::with_name("graphml")
+                            .long("graphml")
+                            .help("Output in GraphML format (only with --cooccurrence)")
+                            .requires("cooccurrence"))
+                   .arg(Arg::with_name("gexf")
+                            .long("gexf")
+                            .help("Output in GEXF format (only with --cooccurrence)")
+                            .requires("cooccurrence"))
+                   .arg(Arg::with_name("dump-layout")
+                            .long("dump-layout")
+                            .help("Output the layout used for parsing poppler input as toml")
+                            .conflicts_with_all(&["characters", "cooccurrence", "narrative"]))
+                   .arg(Arg::with_name("suggest-aliases")
+                            .long("suggest-aliases")
+                            .help("Output likely aliases of the characters as toml for --aliases")
+                            .conflicts_with_all(&["characters", "cooccurrence", "narrative"]))
                    .arg_group(ArgGroup::with_name("output-format")
-                                       .add_all(&["xml", "json"])
+                                       .add_all(&["xml", "json", "fountain", "fdx", "format", "csv",
+                                                  "graphml", "gexf", "dump-layout", "suggest-aliases"])
                                        .required(true))
+                   .arg(Arg::with_name("characters")
+                            .long("characters")
+                            .help("Output the characters instead of the script")
+                            .conflicts_with_all(&["xml", "fountain", "fdx"]))
+                   .arg(Arg::with_name("cooccurrence")
+                            .long("cooccurrence")
+                            .help("Output how often the characters appear together")
+                            .conflicts_with_all(&["characters", "xml", "fountain", "fdx", "csv"]))
+                   .arg(Arg::with_name("granularity")
+                            .help("Count co-occurrences per scene or per location (default: scene)")
+                            .long("granularity")
+                            .takes_value(true)
+                            .possible_values(&["scene", "location"])
+                            .requires("cooccurrence"))
+                   .arg(Arg::with_name("weighting")
+                            .help("Weight co-occurrences by shared scenes or by dialog exchanges \
+                                   (default: scenes)")
+                            .long("weighting")
+                            .takes_value(true)
+                            .possible_values(&["scenes", "exchanges"])
+                            .requires("cooccurrence"))
+                   .arg(Arg::with_name("narrative")
+                            .long("narrative")
+                            .help("Output the storyline for narrative charts")
+                            .conflicts_with_all(&["characters", "cooccurrence", "xml", "fountain",
+                                                  "fdx", "csv"]))
+                   .arg(Arg::with_name("duration")
+                            .help("Measure the duration of scenes in pages or words (default: pages)")
+                            .long("duration")
+                            .takes_value(true)
+                            .possible_values(&["pages", "words"])
+                            .requires("narrative"))
+                   .arg(Arg::with_name("top-characters")
+                            .help("Only show the given number of most frequent characters")
+                            .long("top-characters")
+                            .takes_value(true)
+                            .validator(check_number)
+                            .requires("narrative"))
+                   .arg(Arg::with_name("max-characters")
+                            .help("Only show the most frequent characters in scenes with more \
+                                   characters (default: 5)")
+                            .long("max-characters")
+                            .takes_value(true)
+                            .validator(check_number)
+                            .requires("narrative"))
+                   .arg(Arg::with_name("frequent-characters")
+                            .help("Number of most frequent characters shown in scenes with more \
+                                   than --max-characters (default: 5)")
+                            .long("frequent-characters")
+                            .takes_value(true)
+                            .validator(check_number)
+                            .requires("narrative"))
+                   .arg(Arg::with_name("group")
+                            .help("Put a character into a group, like 'WOODY=2' (default group: 1)")
+                            .long("group")
+                            .takes_value(true)
+                            .multiple(true)
+                            .validator(|v| if extract_group(&v).is_some() {
+                                               Ok(())
+                                           } else {
+                                               Err(format!("Invalid group '{}'", v))
+                                           })
+                            .requires("narrative"))
+                   .arg(Arg::with_name("heading-prefix")
+                            .help("Recognize scene headings starting with the given prefix, \
+                                   like 'CAR=internal,external' (types: internal, external, \
+                                   internal,external, establishing)")
+                            .long("heading-prefix")
+                            .takes_value(true)
+                            .multiple(true)
+                            .validator(|v| if extract_heading_prefix(&v).is_some() {
+                                               Ok(())
+                                           } else {
+                                               Err(format!("Invalid heading prefix '{}'", v))
+                                           }))
+                   .arg(Arg::with_name("scene-transitions")
+                            .help("Transitions which start a new scene \
+                                   (default: cut smash_cut match_cut dissolve other)")
+                            .long("scene-transitions")
+                            .takes_value(true)
+                            .multiple(true)
+                            .possible_values(&["cut", "smash_cut", "match_cut", "dissolve", "fade_in",
+                                               "fade_out", "intercut", "other"]))
+                   .arg(Arg::with_name("bold-headings")
+                            .help("Treat bold uppercase lines standing alone in the direction column \
+                                   of poppler input as scene headings")
+                            .long("bold-headings"))
+                   .arg(Arg::with_name("aliases")
+                            .help("Map the variants of characters' names to canonical names using \
+                                   a toml or json file")
+                            .long("aliases")
+                            .takes_value(true)
+                            .validator(check_file_exists))
+                   .arg(Arg::with_name("layout")
+                            .help("Read the layout of poppler input from a toml or json file \
+                                   instead of detecting it")
+                            .long("layout")
+                            .takes_value(true)
+                            .validator(check_file_exists))
+                   .arg(Arg::with_name("direction-column")
+                            .help("Left position of directions in poppler input")
+                            .long("direction-column")
+                            .takes_value(true)
+                            .validator(check_number))
+                   .arg(Arg::with_name("dialog-column")
+                            .help("Left position of dialogs in poppler input")
+                            .long("dialog-column")
+                            .takes_value(true)
+                            .validator(check_number))
+                   .arg(Arg::with_name("speaker-column")
+                            .help("Left position of speakers in poppler input")
+                            .long("speaker-column")
+                            .takes_value(true)
+                            .validator(check_number))
+                   .arg(Arg::with_name("parenthetical-column")
+                            .help("Left position of parentheticals in poppler input")
+                            .long("parenthetical-column")
+                            .takes_value(true)
+                            .validator(check_number))
+                   .arg(Arg::with_name("transition-column")
+                            .help("Left position of transitions in poppler input")
+                            .long("transition-column")
+                            .takes_value(true)
+                            .validator(check_number))
+                   .arg(Arg::with_name("column-tolerance")
+                            .help("Maximum distance of lines from the left position of their \
+                                   column in poppler input")
+                            .long("column-tolerance")
+                            .takes_value(true)
+                            .validator(check_number))
+                   .arg(Arg::with_name("section-gap")
+                            .help("Minimum vertical distance between sections in poppler input \
+                                   (default: 18)")
+                            .long("section-gap")
+                            .takes_value(true)
+                            .validator(check_number))
+                   .arg(Arg::with_name("diagnostics")
+                            .help("Write the skipped lines and layout decisions of parsing \
+                                   poppler input to the given file as json")
+                            .long("diagnostics")
+                            .takes_value(true))
                    .arg(Arg::with_name("pages")
                             .help("Specify a single page or range of pages to extract")
                             .short("p")
@@ -38,6 +229,13 @@ fn main() {
                                            } else {
                                                Err(format!("Invalid page range '{}'", v))
                                            }))
+                   .arg(Arg::with_name("page-filter")
+                            .help("Keep the parts overlapping the page range or only those fully within it \
+                                   (default: overlaps)")
+                            .long("page-filter")
+                            .takes_value(true)
+                            .possible_values(&["overlaps", "within"])
+                            .requires("pages"))
                    .get_matches();
 
     let mut input: Box<Read> = if let Some(input_file) = args.value_of("input-file") {
@@ -51,21 +249,255 @@ fn main() {
         Box::new(BufReader::new(std::io::stdin()))
     };
 
-    let mut script = parse::parse_script(&mut input);
+    let mut rules: parse::ParseRules = Default::default();
+    for heading_prefix in args.values_of("heading-prefix").unwrap_or(Vec::new()) {
+        if let Some((prefix, kind)) = extract_heading_prefix(heading_prefix) {
+            rules.headings.add(&prefix, kind);
+        }
+    }
+    if let Some(transitions) = args.values_of("scene-transitions") {
+        rules.scene_transitions = transitions.iter().filter_map(|kind| kind.parse().ok()).collect();
+    }
+    rules.bold_headings = args.is_present("bold-headings");
+
+    let mut diagnostics = None;
+    let parsed_script = match args.value_of("input-format").unwrap_or("poppler") {
+        "fountain" => parse::fountain::parse_script_with_rules(&mut input, &rules),
+        "fdx" => parse::fdx::parse_script_with_rules(&mut input, &rules),
+        "json" => serialize::json::parse_script(&mut input),
+        "xml" => serialize::xml::parse_script(&mut input),
+        _ => {
+            let (script, parse_diagnostics) =
+                parse::parse_script_with_layout(&mut input, &rules, |detected| choose_layout(detected, &args));
+            if parse_diagnostics.skipped_lines.len() > 0 {
+                warn_unmatched_lines(&parse_diagnostics.skipped_lines);
+            }
+            diagnostics = Some(parse_diagnostics);
+            script
+        }
+    };
+
+    // the diagnostics are written before failing, as they help to find out
+    // why a script could not be parsed
+    if let Some(diagnostics_file) = args.value_of("diagnostics") {
+        let result = match diagnostics {
+            Some(ref diagnostics) => File::create(diagnostics_file).and_then(|mut file| {
+                parse::diagnostics::format_json(diagnostics, &mut file)
+            }).map_err(|e| format!("Cannot write diagnostics: {}", e)),
+            None => Err("Only poppler input has diagnostics".to_string()),
+        };
+
+        if let Err(message) = result {
+            writeln!(std::io::stderr(), "Error: {}", message).ok();
+            std::process::exit(1);
+        }
+    }
+
+    let mut script = match parsed_script {
+        Ok(script) => script,
+        Err(e) => {
+            writeln!(std::io::stderr(), "Error: {}", e).ok();
+            std::process::exit(1);
+        }
+    };
+
+    if args.is_present("dump-layout") {
+        match diagnostics {
+            Some(diagnostics) => print!("{}", diagnostics.layout.to_toml()),
+            None => {
+                writeln!(std::io::stderr(), "Error: Only poppler input has a layout").ok();
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
     // filter by pages if requested
     if let Some(range_string) = args.value_of("pages") {
         let range = extract_range(range_string).unwrap_or((0,u32::max_value()));
-        script = filter_script(script, range);
+        let filter = match args.value_of("page-filter") {
+            Some("within") => PageFilter::Within,
+            _ => PageFilter::Overlaps,
+        };
+        script = filter_script(script, range, filter);
     }
 
-    if args.is_present("xml") {
-        serialize::xml::format_script(&script, &mut std::io::stdout()).unwrap();
-    } else if args.is_present("json") {
-        serialize::json::format_script(&script, &mut std::io::stdout()).unwrap();
+    if let Some(aliases_file) = args.value_of("aliases") {
+        match read_aliases(aliases_file) {
+            Ok(aliases) => script = aliases::apply_aliases(script, &aliases),
+            Err(e) => {
+                writeln!(std::io::stderr(), "Error: {}", e).ok();
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if !args.is_present("with-positions") {
+        script = strip_source_spans(script);
+    }
+    if !args.is_present("with-emphasis") {
+        script = strip_emphasis(script);
+    }
+
+    if args.is_present("suggest-aliases") {
+        let suggestions = aliases::suggest_aliases(&script);
+        aliases::format_toml(&suggestions, &mut std::io::stdout()).unwrap();
+    } else if args.is_present("characters") {
+        let characters = characters::extract_characters(&script);
+        if args.is_present("csv") {
+            characters::format_csv(&characters, &mut std::io::stdout()).unwrap();
+        } else {
+            characters::format_json(&characters, &mut std::io::stdout()).unwrap();
+        }
+    } else if args.is_present("cooccurrence") {
+        let granularity = match args.value_of("granularity") {
+            Some("location") => cooccurrence::Granularity::Location,
+            _ => cooccurrence::Granularity::Scene,
+        };
+        let weighting = match args.value_of("weighting") {
+            Some("exchanges") => cooccurrence::Weighting::DialogExchanges,
+            _ => cooccurrence::Weighting::SharedScenes,
+        };
+
+        let cooccurrences = cooccurrence::compute_cooccurrences(&script, granularity, weighting);
+        if args.is_present("graphml") {
+            cooccurrence::format_graphml(&cooccurrences, &mut std::io::stdout()).unwrap();
+        } else if args.is_present("gexf") {
+            cooccurrence::format_gexf(&cooccurrences, &mut std::io::stdout()).unwrap();
+        } else {
+            cooccurrence::format_json(&cooccurrences, &mut std::io::stdout()).unwrap();
+        }
+    } else if args.is_present("narrative") {
+        let mut options: narrative::NarrativeOptions = Default::default();
+        if let Some("words") = args.value_of("duration") {
+            options.duration = narrative::DurationMeasure::Words;
+        }
+        // the numbers are validated by check_number
+        if let Some(top_characters) = args.value_of("top-characters") {
+            options.top_characters = Some(top_characters.parse().unwrap());
+        }
+        if let Some(max_characters) = args.value_of("max-characters") {
+            options.max_characters = max_characters.parse().unwrap();
+        }
+        if let Some(frequent_characters) = args.value_of("frequent-characters") {
+            options.frequent_characters = frequent_characters.parse().unwrap();
+        }
+        for group in args.values_of("group").unwrap_or(Vec::new()) {
+            if let Some((name, group)) = extract_group(group) {
+                options.groups.insert(name, group);
+            }
+        }
+
+        let narrative = narrative::extract_narrative(&script, &options);
+        narrative::format_json(&narrative, &mut std::io::stdout()).unwrap();
+    } else {
+        let format = args.value_of("format").unwrap_or_else(|| {
+            ["xml", "json", "fountain", "fdx"].iter().cloned().find(|&format| args.is_present(format)).unwrap_or("json")
+        });
+
+        let mut output = std::io::stdout();
+        match format {
+            "xml" => serialize::xml::format_script(&script, &mut output).unwrap(),
+            "fountain" => serialize::fountain::format_script(&script, &mut output).unwrap(),
+            "fdx" => serialize::fdx::format_script(&script, &mut output).unwrap(),
+            "yaml" => serialize::yaml::format_script(&script, &mut output).unwrap(),
+            "msgpack" => serialize::msgpack::format_script(&script, &mut output).unwrap(),
+            "cbor" => serialize::cbor::format_script(&script, &mut output).unwrap(),
+            "ron" => serialize::ron::format_script(&script, &mut output).unwrap(),
+            _ => serialize::json::format_script(&script, &mut output).unwrap(),
+        }
     }
 }
 
+/// Selects the layout used for parsing poppler input.
+///
+/// Uses the layout file if given and the detected layout otherwise, which
+/// is not required if all three main columns are given. The columns given
+/// as flags replace those of the layout.
+fn choose_layout(detected: Result<LayoutProfile, ParseError>, args: &ArgMatches)
+    -> Result<LayoutProfile, ParseError> {
+    let mut layout = if let Some(layout_file) = args.value_of("layout") {
+        let mut input = String::new();
+        if let Err(e) = File::open(layout_file).and_then(|mut file| file.read_to_string(&mut input)) {
+            return Err(ParseError::Io { message: e.to_string() });
+        }
+
+        if layout_file.ends_with(".json") {
+            try!(LayoutProfile::from_json(&input))
+        } else {
+            try!(LayoutProfile::from_toml(&input))
+        }
+    } else if args.is_present("direction-column") && args.is_present("dialog-column") &&
+              args.is_present("speaker-column") {
+        detected.unwrap_or(Default::default())
+    } else {
+        try!(detected)
+    };
+
+    let column = |name: &str| args.value_of(name).and_then(|v| v.parse().ok()).map(Column::new);
+    if let Some(direction) = column("direction-column") {
+        layout.direction = direction;
+    }
+    if let Some(dialog) = column("dialog-column") {
+        layout.dialog = dialog;
+    }
+    if let Some(speaker) = column("speaker-column") {
+        layout.speaker = speaker;
+    }
+    if let Some(speaker_direction) = column("parenthetical-column") {
+        layout.speaker_direction = Some(speaker_direction);
+    }
+    if let Some(transition) = column("transition-column") {
+        layout.transition = Some(transition);
+    }
+
+    if let Some(tolerance) = args.value_of("column-tolerance").and_then(|v| v.parse().ok()) {
+        layout.direction.tolerance = tolerance;
+        layout.dialog.tolerance = tolerance;
+        layout.speaker.tolerance = tolerance;
+        if let Some(ref mut speaker_direction) = layout.speaker_direction {
+            speaker_direction.tolerance = tolerance;
+        }
+        if let Some(ref mut transition) = layout.transition {
+            transition.tolerance = tolerance;
+        }
+    }
+    if let Some(section_gap) = args.value_of("section-gap").and_then(|v| v.parse().ok()) {
+        layout.section_gap = section_gap;
+    }
+
+    Ok(layout)
+}
+
+/// Reads the aliases of the characters from a toml or json file.
+fn read_aliases(aliases_file: &str) -> Result<aliases::Aliases, ParseError> {
+    let mut input = String::new();
+    if let Err(e) = File::open(aliases_file).and_then(|mut file| file.read_to_string(&mut input)) {
+        return Err(ParseError::Io { message: e.to_string() });
+    }
+
+    if aliases_file.ends_with(".json") {
+        aliases::Aliases::from_json(&input)
+    } else {
+        aliases::Aliases::from_toml(&input)
+    }
+}
+
+/// Warns about lines which were skipped because they match no column,
+/// together with their positions to help adjusting the layout.
+fn warn_unmatched_lines(unmatched_lines: &Vec<parse::diagnostics::UnmatchedLine>) {
+    let mut position_uses: BTreeMap<i32, u32> = BTreeMap::new();
+    for line in unmatched_lines.iter() {
+        *position_uses.entry(line.left).or_insert(0) += 1;
+    }
+
+    let positions: Vec<String> = position_uses.iter()
+                                               .map(|(position, uses)| format!("{} ({}x)", position, uses))
+                                               .collect();
+    writeln!(std::io::stderr(), "Warning: Skipped {} lines matching no column, found at: {}",
+             unmatched_lines.len(), positions.join(", ")).ok();
+}
+
 /// Check if the given file exists. Also considers "-" as a valid file.
 fn check_file_exists(file_name: String) -> Result<(), String> {
     if file_name == "-" {
@@ -80,3 +512,31 @@ fn check_file_exists(file_name: String) -> Result<(), String> {
         Err(format!("File '{}' not found", file_name))
     }
 }
+
+/// Check if the given value is a non-negative number.
+fn check_number(value: String) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("Invalid number '{}'", value)),
+    }
+}
+
+/// Parses a heading prefix like "CAR=internal,external" into the prefix
+/// and its location type.
+fn extract_heading_prefix(heading_prefix: &str) -> Option<(String, LocationType)> {
+    let mut split = heading_prefix.splitn(2, '=');
+    match (split.next(), split.next().map(|kind| kind.parse())) {
+        (Some(prefix), Some(Ok(kind))) if prefix.len() > 0 => Some((prefix.to_string(), kind)),
+        _ => None,
+    }
+}
+
+/// Parses a character group like "WOODY=2" into the normalized name and
+/// the group.
+fn extract_group(group: &str) -> Option<(String, u32)> {
+    let mut split = group.rsplitn(2, '=');
+    match (split.next().map(|g| g.parse()), split.next()) {
+        (Some(Ok(group)), Some(name)) => Some((characters::normalize_name(name), group)),
+        _ => None,
+    }
+}
@@ -0,0 +1,184 @@
+//! Exporting the storyline of a `Script` for narrative charts.
+//!
+//! The exported data is the input of the flow graph of the script-explorer
+//! (`narrative.js`). Each `Location` of the script becomes one scene of the
+//! chart, connecting the characters speaking in it.
+
+/// How the duration of a scene in the chart is measured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DurationMeasure {
+    /// The number of pages the location spans
+    Pages,
+    /// The number of words of all directions and dialogs of the location
+    Words,
+}
+
+/// Options to reduce the characters shown in the chart.
+#[derive(Debug, Clone)]
+pub struct NarrativeOptions {
+    pub duration: DurationMeasure,
+    /// Only show this number of most frequent characters in all scenes
+    pub top_characters: Option<usize>,
+    /// Scenes with more characters only show the most frequent characters
+    pub max_characters: usize,
+    /// The number of most frequent characters kept in scenes with more than
+    /// `max_characters`, unless `top_characters` is given
+    pub frequent_characters: usize,
+    /// The group of each character, characters without a group are put in
+    /// group 1
+    pub groups: BTreeMap<String, u32>,
+}
+
+/// A single scene of the chart.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NarrativeScene {
+    pub id: usize,
+    /// The sum of the durations of all previous scenes
+    pub start: u32,
+    pub duration: u32,
+    /// The ids of the characters appearing in the scene
+    #[serde(rename = "chars")]
+    pub characters: Vec<usize>,
+}
+
+/// The storyline of a `Script`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Narrative {
+    /// The scenes in the order of the script
+    #[serde(rename = "narrative")]
+    pub scenes: Vec<NarrativeScene>,
+    /// The ids of the characters by their normalized name
+    #[serde(rename = "chars")]
+    pub characters: BTreeMap<String, usize>,
+    /// The groups of the characters by their normalized name
+    pub groups: BTreeMap<String, u32>,
+}
+
+/// By default the duration is measured in pages and scenes with more than
+/// 5 characters only show the 5 most frequent ones, like in the
+/// script-explorer.
+impl Default for NarrativeOptions {
+    fn default() -> NarrativeOptions {
+        NarrativeOptions {
+            duration: DurationMeasure::Pages,
+            top_characters: None,
+            max_characters: 5,
+            frequent_characters: 5,
+            groups: BTreeMap::new(),
+        }
+    }
+}
+
+/// Extracts the storyline of the given `Script`.
+///
+/// Scenes without any of the shown characters are left out, but still
+/// count towards the start of the following scenes.
+pub fn extract_narrative(script: &Script, options: &NarrativeOptions) -> Narrative {
+    let frequent: Vec<String> = extract_characters(script).into_iter()
+                                                          .take(options.top_characters
+                                                                       .unwrap_or(options.frequent_characters))
+                                                          .map(|character| character.name)
+                                                          .collect();
+
+    let mut narrative: Narrative = Default::default();
+    let mut start = 0;
+
+    for location in script.scenes.iter().flat_map(|scene| scene.iter()) {
+        let mut names: Vec<String> = location.parts.iter().flat_map(speaking_characters).collect();
+        names.sort();
+        names.dedup();
+
+        if options.top_characters.is_some() || names.len() > options.max_characters {
+            names.retain(|name| frequent.contains(name));
+        }
+
+        let duration = measure_duration(location, options.duration);
+
+        if names.len() > 0 {
+            // ids are assigned in the order of the first appearance
+            let mut characters: Vec<usize> = names.into_iter().map(|name| {
+                let next_id = narrative.characters.len();
+                *narrative.characters.entry(name).or_insert(next_id)
+            }).collect();
+            characters.sort();
+
+            narrative.scenes.push(NarrativeScene {
+                id: narrative.scenes.len(),
+                start: start,
+                duration: duration,
+                characters: characters,
+            });
+        }
+
+        start += duration;
+    }
+
+    for name in narrative.characters.keys() {
+        let group = options.groups.get(name).cloned().unwrap_or(1);
+        narrative.groups.insert(name.clone(), group);
+    }
+
+    narrative
+}
+
+/// Serialize the given storyline into a `json`
+///
+/// # Example
+///
+/// ```json
+/// {
+///   "narrative": [
+///     { "id": 0, "start": 0, "duration": 2, "chars": [0, 1] },
+///     { "id": 1, "start": 2, "duration": 1, "chars": [0] }
+///   ],
+///   "chars": { "SHAMAN": 0, "SINTEL": 1 },
+///   "groups": { "SHAMAN": 1, "SINTEL": 2 }
+/// }
+/// ```
+pub fn format_json<W: Write>(narrative: &Narrative, output: &mut W) -> io::Result<()> {
+    try!(serde_json::to_writer_pretty(&mut *output, narrative));
+    try!(writeln!(output));
+    output.flush()
+}
+
+
+use ::{Dialog, DialogPart, Location, ScenePart, Script};
+use characters::{extract_characters, speaking_characters};
+use serde_json;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+fn measure_duration(location: &Location, measure: DurationMeasure) -> u32 {
+    match measure {
+        DurationMeasure::Pages => {
+            let pages = location.parts.iter().map(|part| part.page());
+            match (pages.clone().min(), pages.max()) {
+                (Some(first), Some(last)) => last - first + 1,
+                _ => 0,
+            }
+        }
+        DurationMeasure::Words => {
+            location.parts.iter().map(count_part_words).sum()
+        }
+    }
+}
+
+fn count_part_words(part: &ScenePart) -> u32 {
+    match part {
+        &ScenePart::Direction { ref direction, .. } => count_words(direction),
+        &ScenePart::Dialog(ref dialog) => count_dialog_words(dialog),
+        &ScenePart::DualDialog { ref left, ref right } => count_dialog_words(left) + count_dialog_words(right),
+        &ScenePart::Transition { .. } => 0,
+    }
+}
+
+fn count_dialog_words(dialog: &Dialog) -> u32 {
+    dialog.dialog.iter().map(|dialog_part| match dialog_part {
+        &DialogPart::Dialog(ref text, _, _) => count_words(text),
+        &DialogPart::Direction(ref text, _, _) => count_words(text),
+    }).sum()
+}
+
+fn count_words(text: &str) -> u32 {
+    text.split_whitespace().count() as u32
+}
@@ -0,0 +1,145 @@
+//! Collecting diagnostics while parsing scripts extracted from pdfs.
+//!
+//! Lines which match no column of the layout are skipped by the parser. The
+//! `Diagnostics` record these lines together with the heuristic decisions
+//! of the parser, so it can be checked how much of the text of a script
+//! made it into the extracted `Script`. The page artifacts like page numbers,
+//! which are removed before parsing, are recorded as well.
+
+/// A line of the script which did not match any column of the layout.
+///
+/// These are usually page numbers or headers, but can also be text of a
+/// column which was not detected.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnmatchedLine {
+    pub page: u32,
+    pub top: i32,
+    pub left: i32,
+    pub text: String,
+}
+
+/// A column found while detecting the layout.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DetectedColumn {
+    #[serde(flatten)]
+    pub column: Column,
+    /// The number of lines starting in the column
+    pub uses: u32,
+}
+
+/// A position at which the parser started a new section.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SectionBreak {
+    pub page: u32,
+    pub top: i32,
+    /// The vertical distance to the previous line, which is negative at the
+    /// start of a new page
+    pub gap: i32,
+}
+
+/// The diagnostics of parsing a single script.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    /// The number of non-empty lines of the script
+    pub lines: u32,
+    /// The number of characters of all non-empty lines
+    pub characters: u32,
+    /// The number of characters of all skipped lines
+    pub skipped_characters: u32,
+    /// All columns found in the script, sorted by uses descending
+    pub columns: Vec<DetectedColumn>,
+    /// The detected layout, if the detection succeeded
+    pub detected_layout: Option<LayoutProfile>,
+    /// The layout actually used for parsing
+    pub layout: LayoutProfile,
+    pub section_breaks: Vec<SectionBreak>,
+    pub skipped_lines: Vec<UnmatchedLine>,
+    /// The lines removed as page artifacts, like page numbers, running
+    /// headers, `(MORE)` and `(CONTINUED)`
+    pub artifacts: Vec<UnmatchedLine>,
+    /// The error which stopped parsing the script, if any
+    pub error: Option<String>,
+}
+
+impl Diagnostics {
+    /// Returns the fraction of characters which were not skipped.
+    ///
+    /// A script without any text has a coverage of 1.
+    pub fn coverage(&self) -> f64 {
+        if self.characters == 0 {
+            1.0
+        } else {
+            1.0 - self.skipped_characters as f64 / self.characters as f64
+        }
+    }
+}
+
+/// Serialize the given diagnostics into a `json`
+///
+/// # Example
+///
+/// ```json
+/// {
+///   "lines": 1843,
+///   "characters": 98234,
+///   "skipped_characters": 312,
+///   "coverage": 0.9968,
+///   "columns": [
+///     { "position": 108, "tolerance": 1, "uses": 1021 }
+///   ],
+///   "detected_layout": {
+///     "direction": { "position": 108, "tolerance": 1 },
+///     "dialog": { "position": 180, "tolerance": 0 },
+///     "speaker_direction": null,
+///     "speaker": { "position": 252, "tolerance": 0 },
+///     "transition": null,
+///     "section_gap": 18,
+///     "line_height": 12
+///   },
+///   "layout": { "direction": { "position": 108, "tolerance": 1 }, (...) },
+///   "section_breaks": [
+///     { "page": 1, "top": 120, "gap": 120 }
+///   ],
+///   "skipped_lines": [
+///     { "page": 3, "top": 480, "left": 612, "text": "OVER BLACK" }
+///   ],
+///   "artifacts": [
+///     { "page": 2, "top": 54, "left": 504, "text": "2." }
+///   ]
+/// }
+/// ```
+///
+/// If parsing failed, the `error` is written as well, like
+/// `"error": "Script uses strange layout, found columns: (...)"`.
+pub fn format_json<W: Write>(diagnostics: &Diagnostics, output: &mut W) -> io::Result<()> {
+    try!(serde_json::to_writer_pretty(&mut *output, diagnostics));
+    try!(writeln!(output));
+    output.flush()
+}
+
+
+use parse::layout::{Column, LayoutProfile};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json;
+use std::io::{self, Write};
+
+/// The `coverage` is written after the counts it is computed from.
+impl Serialize for Diagnostics {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = try!(serializer.serialize_struct("Diagnostics", 10 + self.error.iter().count()));
+        try!(s.serialize_field("lines", &self.lines));
+        try!(s.serialize_field("characters", &self.characters));
+        try!(s.serialize_field("skipped_characters", &self.skipped_characters));
+        try!(s.serialize_field("coverage", &self.coverage()));
+        try!(s.serialize_field("columns", &self.columns));
+        try!(s.serialize_field("detected_layout", &self.detected_layout));
+        try!(s.serialize_field("layout", &self.layout));
+        try!(s.serialize_field("section_breaks", &self.section_breaks));
+        try!(s.serialize_field("skipped_lines", &self.skipped_lines));
+        try!(s.serialize_field("artifacts", &self.artifacts));
+        if let Some(ref error) = self.error {
+            try!(s.serialize_field("error", error));
+        }
+        s.end()
+    }
+}
@@ -0,0 +1,267 @@
+//! Parsing scripts in the Final Draft (`.fdx`) format.
+
+/// Parses the given Final Draft script into a `Script`.
+///
+/// Reads a script in the xml-based Final Draft format from `reader` and
+/// parses its paragraphs into a `Script`. Scene headings start new
+/// `Location`s. Transitions are kept as `ScenePart`s and start new `Scene`s.
+///
+/// Pages are counted using the `StartsNewPage` attribute of the paragraphs,
+/// starting with page 1. Scene numbers are read from the `Number` attribute
+/// of the scene headings. The title page is parsed into the
+/// `ScriptMetadata`, while headers and footers are skipped. The two dialogs
+/// of a `DualDialogue` are combined into a `DualDialog`.
+pub fn parse_script(reader: &mut Read) -> Result<Script, ParseError> {
+    parse_script_with_rules(reader, &Default::default())
+}
+
+/// Parses the given Final Draft script into a `Script` using custom rules.
+///
+/// Works like `parse_script`, but splits the prefixes off the scene headings
+/// and starts new `Scene`s using the given `ParseRules`. As the headings are
+/// marked, their prefixes are matched regardless of their case.
+pub fn parse_script_with_rules(reader: &mut Read, rules: &ParseRules) -> Result<Script, ParseError> {
+    let mut rules = rules.clone();
+    rules.headings.ignore_case = true;
+
+    let (paragraphs, title_paragraphs) = try!(read_paragraphs(reader));
+
+    let parts = extract_script_parts(&paragraphs);
+
+    Ok(Script {
+        metadata: extract_title_page(&title_paragraphs),
+        scenes: extract_scenes(&parts, &rules),
+    })
+}
+
+
+use ::{Dialog, DialogPart, ScenePart, Script, ScriptMetadata, TransitionKind};
+use super::{ParseError, ParseRules, ScriptPart, extract_scenes, extract_speaker};
+use super::title_page::extract_metadata;
+use std::io::Read;
+use xml::EventReader;
+use xml::reader::XmlEvent;
+
+#[derive(Debug, Clone, Default)]
+struct Paragraph {
+    kind: String,
+    text: String,
+    page: u32,
+    number: Option<String>,
+    /// The number of the `DualDialogue` containing the paragraph
+    dual_dialogue: Option<u32>,
+}
+
+/// Reads the typed paragraphs from the content of the script and the
+/// paragraphs of the title page.
+///
+/// The text of a paragraph can be split into several `Text` elements with
+/// different styles, which are joined together.
+fn read_paragraphs(reader: &mut Read) -> Result<(Vec<Paragraph>, Vec<Paragraph>), ParseError> {
+    let mut paragraphs: Vec<Paragraph> = Vec::new();
+    let mut title_paragraphs: Vec<Paragraph> = Vec::new();
+
+    // states for the streaming xml parsing
+    let mut element_path: Vec<String> = Vec::new();
+    let mut current_paragraph: Option<Paragraph> = None;
+    let mut current_page_number = 1;
+    let mut dual_dialogues = 0;
+
+    let parser = EventReader::new(reader);
+    for event in parser {
+        match try!(event) {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                // the script itself is only contained in FinalDraft/Content and
+                // the title page in FinalDraft/TitlePage/Content, which excludes
+                // headers and footers
+                let in_content = element_path.len() >= 2 &&
+                                 element_path[0] == "FinalDraft" &&
+                                 element_path[1] == "Content";
+                let in_title_page = element_path.len() >= 3 &&
+                                    element_path[0] == "FinalDraft" &&
+                                    element_path[1] == "TitlePage" &&
+                                    element_path[2] == "Content";
+
+                if in_content && name.local_name == "DualDialogue" {
+                    dual_dialogues += 1;
+                }
+
+                if in_title_page && name.local_name == "Paragraph" {
+                    // the paragraphs of the title page are untyped
+                    current_paragraph = Some(Default::default());
+                } else if in_content && name.local_name == "Paragraph" {
+                    let mut paragraph: Paragraph = Default::default();
+                    for attr in attributes {
+                        match attr.name.local_name.as_ref() {
+                            "Type" => paragraph.kind = attr.value,
+                            "Number" => paragraph.number = Some(attr.value),
+                            "StartsNewPage" if attr.value == "Yes" && paragraphs.len() > 0 => {
+                                current_page_number += 1;
+                            }
+                            _ => {}
+                        }
+                    }
+                    paragraph.page = current_page_number;
+                    if element_path.iter().any(|element| element == "DualDialogue") {
+                        paragraph.dual_dialogue = Some(dual_dialogues);
+                    }
+
+                    // dual dialogue is wrapped in an untyped paragraph
+                    if paragraph.kind.len() > 0 {
+                        current_paragraph = Some(paragraph);
+                    }
+                }
+
+                element_path.push(name.local_name);
+            }
+            XmlEvent::Characters(text) | XmlEvent::Whitespace(text) | XmlEvent::CData(text) => {
+                if let (Some(paragraph), Some("Text")) = (current_paragraph.as_mut(),
+                                                          element_path.last().map(|s| s.as_ref())) {
+                    paragraph.text.push_str(&text);
+                }
+            }
+            XmlEvent::EndElement { name } => {
+                element_path.pop();
+
+                if name.local_name == "Paragraph" {
+                    if let Some(mut paragraph) = current_paragraph.take() {
+                        paragraph.text = paragraph.text.split_whitespace()
+                                                       .collect::<Vec<_>>()
+                                                       .join(" ");
+                        if element_path.get(1).map_or(false, |element| element == "TitlePage") {
+                            title_paragraphs.push(paragraph);
+                        } else {
+                            paragraphs.push(paragraph);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok((paragraphs, title_paragraphs))
+}
+
+/// Extracts the `ScriptMetadata` from the paragraphs of the title page.
+///
+/// Empty paragraphs separate the blocks of the title page.
+fn extract_title_page(paragraphs: &Vec<Paragraph>) -> ScriptMetadata {
+    let mut blocks: Vec<Vec<String>> = vec![Vec::new()];
+    for paragraph in paragraphs.iter() {
+        if paragraph.text.len() > 0 {
+            blocks.last_mut().unwrap().push(paragraph.text.clone());
+        } else if blocks.last().unwrap().len() > 0 {
+            blocks.push(Vec::new());
+        }
+    }
+
+    extract_metadata(&blocks)
+}
+
+fn extract_script_parts(paragraphs: &Vec<Paragraph>) -> Vec<ScriptPart> {
+    let mut script_parts: Vec<ScriptPart> = Vec::new();
+    let mut last_dual_dialogue = None;
+
+    for paragraph in paragraphs.iter() {
+        if paragraph.text.len() == 0 {
+            continue;
+        }
+
+        match paragraph.kind.as_ref() {
+            "Scene Heading" => {
+                script_parts.push(ScriptPart::LocationChange(paragraph.text.clone(),
+                                                             paragraph.number.clone()));
+            }
+            "Transition" => {
+                let kind = TransitionKind::from_text(&paragraph.text.to_uppercase());
+                script_parts.push(ScriptPart::ScenePart(
+                    ScenePart::Transition {
+                        kind: kind.unwrap_or(TransitionKind::Other),
+                        text: paragraph.text.clone(),
+                        page: paragraph.page,
+                        end_page: paragraph.page,
+                        top: None,
+                        end_top: None,
+                        source: Vec::new(),
+                    }));
+            }
+            "Character" => {
+                let (speaker, mode) = extract_speaker(&paragraph.text);
+                let dialog = Dialog {
+                    speaker: speaker,
+                    mode: mode,
+                    dialog: Vec::new(),
+                    page: paragraph.page,
+                    end_page: paragraph.page,
+                    top: None,
+                    end_top: None,
+                    revised: false,
+                    source: Vec::new(),
+                };
+
+                // the second character of a dual dialogue is combined with the first
+                let is_second = paragraph.dual_dialogue.is_some() && paragraph.dual_dialogue == last_dual_dialogue;
+                last_dual_dialogue = paragraph.dual_dialogue;
+
+                match script_parts.pop() {
+                    Some(ScriptPart::ScenePart(ScenePart::Dialog(left))) if is_second => {
+                        script_parts.push(ScriptPart::ScenePart(ScenePart::DualDialog {
+                            left: left,
+                            right: dialog,
+                        }));
+                    }
+                    last_part => {
+                        script_parts.extend(last_part);
+                        script_parts.push(ScriptPart::ScenePart(ScenePart::Dialog(dialog)));
+                    }
+                }
+            }
+            "Parenthetical" | "Dialogue" | "Lyrics" => {
+                // parentheticals and dialogue without a character are ignored,
+                // within a dual dialogue they belong to the second character
+                let last_dialog = match script_parts.last_mut() {
+                    Some(&mut ScriptPart::ScenePart(ScenePart::DualDialog { ref mut right, .. })) => Some(right),
+                    Some(&mut ScriptPart::ScenePart(ScenePart::Dialog(ref mut dialog))) => Some(dialog),
+                    _ => None,
+                };
+                if let Some(&mut Dialog { ref mut dialog, ref mut end_page, .. }) = last_dialog {
+                    let is_parenthetical = paragraph.kind == "Parenthetical";
+                    *end_page = paragraph.page;
+
+                    // merge consecutive paragraphs of dialogue
+                    match dialog.last_mut() {
+                        Some(&mut DialogPart::Dialog(ref mut text, _, _)) if !is_parenthetical => {
+                            text.push(' ');
+                            text.push_str(&paragraph.text);
+                            continue;
+                        }
+                        _ => {}
+                    }
+
+                    if is_parenthetical {
+                        dialog.push(DialogPart::Direction(paragraph.text.clone(), Vec::new(), Vec::new()));
+                    } else {
+                        dialog.push(DialogPart::Dialog(paragraph.text.clone(), Vec::new(), Vec::new()));
+                    }
+                }
+            }
+            _ => {
+                // Action, General, Shot and any custom paragraph types
+                script_parts.push(ScriptPart::ScenePart(
+                    ScenePart::Direction {
+                        direction: paragraph.text.clone(),
+                        page: paragraph.page,
+                        end_page: paragraph.page,
+                        top: None,
+                        end_top: None,
+                        revised: false,
+                        source: Vec::new(),
+                        emphasis: Vec::new(),
+                    }));
+            }
+        }
+    }
+
+    script_parts
+}
@@ -0,0 +1,370 @@
+//! Parsing scripts in the Fountain format.
+//!
+//! See the [Fountain syntax] for a description of the format.
+//!
+//! [Fountain syntax]: https://fountain.io/syntax
+
+/// Parses the given Fountain script into a `Script`.
+///
+/// Reads a plain-text script in the Fountain format from `reader` and parses
+/// it into a `Script`. Scene headings start new `Location`s. Transitions
+/// like `CUT TO:` are kept as `ScenePart`s and start new `Scene`s.
+///
+/// Fountain has no fixed pages, so the page of each `ScenePart` is counted
+/// using the explicit page breaks (`===`), starting with page 1. The keys
+/// of the title page are read into the `ScriptMetadata`. The boneyard
+/// (`/* */`), notes (`[[ ]]`), sections and synopses are skipped. A dialog
+/// whose character cue is marked with `^` forms a `DualDialog` together
+/// with the preceding dialog.
+pub fn parse_script(reader: &mut Read) -> Result<Script, ParseError> {
+    parse_script_with_rules(reader, &Default::default())
+}
+
+/// Parses the given Fountain script into a `Script` using custom rules.
+///
+/// Works like `parse_script`, but additionally recognizes scene headings
+/// and starts new `Scene`s using the given `ParseRules`. As headings are set
+/// apart by blank lines, their prefixes are matched regardless of their case,
+/// like `int kitchen - day`.
+///
+/// # Examples
+///
+/// ```
+/// # use script_extractor::LocationType;
+/// # use script_extractor::parse::fountain;
+/// let text = "int kitchen - day\n\nWoody sits.\n\nest city skyline\n\nThe sun rises.\n";
+/// let script = fountain::parse_script(&mut text.as_bytes()).unwrap();
+///
+/// let locations: Vec<_> = script.scenes.iter().flat_map(|scene| scene.iter()).collect();
+/// assert_eq!(locations[0].kind, LocationType::Internal);
+/// assert_eq!(locations[0].primary_place, "kitchen");
+/// assert_eq!(locations[1].kind, LocationType::Establishing);
+/// assert_eq!(locations[1].name, "city skyline");
+/// ```
+pub fn parse_script_with_rules(reader: &mut Read, rules: &ParseRules) -> Result<Script, ParseError> {
+    let mut rules = rules.clone();
+    rules.headings.ignore_case = true;
+    let rules = &rules;
+
+    let mut text = String::new();
+    try!(reader.read_to_string(&mut text).map_err(|e| {
+        ParseError::Io { message: e.to_string() }
+    }));
+
+    let text = remove_comments(&text.replace("\r\n", "\n"));
+    let lines: Vec<&str> = text.lines().collect();
+
+    let (metadata, lines) = extract_title_page(&lines);
+    let parts = extract_script_parts(lines, rules);
+
+    Ok(Script {
+        metadata: metadata,
+        scenes: extract_scenes(&parts, rules),
+    })
+}
+
+
+use ::{Credit, Dialog, DialogPart, ScenePart, Script, ScriptMetadata, TransitionKind};
+use super::{ParseError, ParseRules, ScriptPart, extract_scenes, extract_speaker};
+use regex::Regex;
+use std::io::Read;
+
+/// Marks the places from which a comment has been removed.
+const COMMENT_MARKER: char = '\u{0}';
+
+lazy_static! {
+    static ref COMMENT: Regex = Regex::new(r"(?s)/\*.*?\*/|\[\[.*?\]\]").unwrap();
+    static ref TITLE_PAGE_KEY: Regex = Regex::new(r"^([A-Za-z][A-Za-z ]*):(.*)$").unwrap();
+    static ref SCENE_NUMBER: Regex = Regex::new(r"\s*#([^#\s]+)#$").unwrap();
+}
+
+/// Removes the boneyard and notes from the script.
+///
+/// Lines which only consisted of comments are removed completely, so that
+/// they do not split up the surrounding paragraphs.
+fn remove_comments(text: &str) -> String {
+    let marker = COMMENT_MARKER.to_string();
+    let marked = COMMENT.replace_all(text, &marker[..]);
+
+    let mut result = String::new();
+    for line in marked.lines() {
+        let is_comment_only = line.contains(COMMENT_MARKER) &&
+                              line.chars().all(|c| c == COMMENT_MARKER || c.is_whitespace());
+        if !is_comment_only {
+            result.extend(line.chars().filter(|&c| c != COMMENT_MARKER));
+            result.push('\n');
+        }
+    }
+
+    result
+}
+
+/// Reads the title page, which consists of `key: value` pairs and ends with
+/// the first empty line.
+///
+/// Values can continue on the following indented lines. Returns the
+/// `ScriptMetadata` and the lines after the title page. A script starting
+/// with a transition like `FADE IN:` has no title page.
+fn extract_title_page<'a>(lines: &'a [&'a str]) -> (ScriptMetadata, &'a [&'a str]) {
+    let mut metadata: ScriptMetadata = Default::default();
+
+    match lines.first() {
+        Some(line) if TITLE_PAGE_KEY.is_match(line) && TransitionKind::from_text(line).is_none() => {}
+        _ => return (metadata, lines),
+    }
+
+    let end = lines.iter().position(|line| line.trim().is_empty()).unwrap_or(lines.len());
+
+    // the key and the lines of its value
+    let mut entries: Vec<(String, Vec<&str>)> = Vec::new();
+    for line in lines[..end].iter() {
+        match TITLE_PAGE_KEY.captures(line) {
+            Some(ref captures) if !line.starts_with(char::is_whitespace) => {
+                let key = captures.at(1).unwrap().trim().to_lowercase();
+                let value = captures.at(2).unwrap().trim();
+                entries.push((key, if value.len() > 0 { vec![value] } else { Vec::new() }));
+            }
+            _ => {
+                if let Some(&mut (_, ref mut values)) = entries.last_mut() {
+                    values.push(line.trim());
+                }
+            }
+        }
+    }
+
+    for (key, values) in entries {
+        if values.is_empty() {
+            continue;
+        }
+
+        match key.as_ref() {
+            "title" => {
+                // remove the emphasis often used for the title
+                let title: Vec<&str> = values.iter()
+                                             .map(|value| value.trim_matches(|c| c == '*' || c == '_'))
+                                             .collect();
+                metadata.title = Some(title.join(" "));
+            }
+            "credit" => metadata.credits.push(Credit {
+                credit: values.join(" "),
+                authors: Vec::new(),
+            }),
+            "author" | "authors" => {
+                // authors without a credit of their own are credited separately
+                let has_credit = metadata.credits.last().map_or(false, |credit| credit.authors.is_empty());
+                if !has_credit {
+                    metadata.credits.push(Default::default());
+                }
+                let credit = metadata.credits.last_mut().unwrap();
+                credit.authors.extend(values.iter().map(|value| value.to_string()));
+            }
+            "source" => metadata.source = Some(values.join(" ")),
+            "draft date" => metadata.draft_date = Some(values.join(" ")),
+            "revision" => metadata.revision = Some(values.join(" ")),
+            "contact" => metadata.contact = Some(values.join("\n")),
+            _ => {}
+        }
+    }
+
+    (metadata, &lines[end..])
+}
+
+fn is_page_break(line: &str) -> bool {
+    line.len() >= 3 && line.chars().all(|c| c == '=')
+}
+
+fn is_scene_heading(line: &str, rules: &ParseRules) -> bool {
+    (line.starts_with('.') && !line.starts_with("..")) || rules.headings.is_heading(line)
+}
+
+/// Splits the forcing `.` and the scene number (`#1A#`) off a heading.
+fn extract_scene_heading(line: &str) -> (String, Option<String>) {
+    let heading = if line.starts_with('.') { &line[1..] } else { line };
+
+    let number = SCENE_NUMBER.captures(heading).map(|captures| captures.at(1).unwrap().to_string());
+    (SCENE_NUMBER.replace(heading, "").trim().to_string(), number)
+}
+
+fn is_transition(line: &str) -> bool {
+    if line.starts_with('>') {
+        // ">centered<" is centered action, not a transition
+        !line.ends_with('<')
+    } else {
+        (is_uppercase(line) && line.ends_with("TO:")) || TransitionKind::from_text(line).is_some()
+    }
+}
+
+/// Removes the marker forcing a line to be a transition.
+fn extract_transition(line: &str) -> &str {
+    line.trim_start_matches('>').trim()
+}
+
+/// Character cues are either forced with `@` or are uppercase, not counting
+/// the extensions and the dual dialogue marker.
+fn is_character(line: &str) -> bool {
+    if line.starts_with('@') {
+        return true;
+    }
+
+    let name = line.trim_end_matches('^').split('(').next().unwrap_or("");
+    is_uppercase(name) && !line.starts_with('!')
+}
+
+fn is_uppercase(text: &str) -> bool {
+    text.chars().any(|c| c.is_alphabetic()) && text.to_uppercase() == text
+}
+
+/// Removes the markers forcing a line to be action.
+fn extract_action(line: &str) -> &str {
+    if line.starts_with('!') {
+        &line[1..]
+    } else if line.starts_with('>') && line.ends_with('<') && line.len() > 1 {
+        line[1..line.len() - 1].trim()
+    } else {
+        line
+    }
+}
+
+fn extract_script_parts(lines: &[&str], rules: &ParseRules) -> Vec<ScriptPart> {
+    let mut script_parts: Vec<ScriptPart> = Vec::new();
+    let mut page = 1;
+
+    let is_blank = |index: usize| {
+        index >= lines.len() || lines[index].trim().is_empty()
+    };
+
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index].trim();
+        let follows_blank = index == 0 || is_blank(index - 1);
+        let precedes_blank = is_blank(index + 1);
+
+        if line.is_empty() {
+            // separates consecutive paragraphs of action
+            script_parts.push(ScriptPart::Separator);
+        } else if is_page_break(line) {
+            page += 1;
+            script_parts.push(ScriptPart::Separator);
+        } else if line.starts_with('#') || (line.starts_with('=') && !line.starts_with("==")) {
+            // sections and synopses are only used for structuring
+        } else if follows_blank && precedes_blank && is_scene_heading(line, rules) {
+            let (heading, number) = extract_scene_heading(line);
+            script_parts.push(ScriptPart::LocationChange(heading, number));
+        } else if follows_blank && precedes_blank && is_transition(line) {
+            let text = extract_transition(line);
+            script_parts.push(ScriptPart::ScenePart(ScenePart::Transition {
+                kind: TransitionKind::from_text(&text.to_uppercase()).unwrap_or(TransitionKind::Other),
+                text: text.to_string(),
+                page: page,
+                end_page: page,
+                top: None,
+                end_top: None,
+                source: Vec::new(),
+            }));
+        } else if follows_blank && !precedes_blank && is_character(line) {
+            let is_dual = line.ends_with('^');
+            let cue = line.trim_start_matches('@').trim_end_matches('^');
+            let (speaker, mode) = extract_speaker(cue);
+
+            let mut dialog: Vec<DialogPart> = Vec::new();
+            while !is_blank(index + 1) {
+                index += 1;
+                append_dialog_line(&mut dialog, lines[index].trim());
+            }
+
+            let dialog = Dialog {
+                speaker: speaker,
+                mode: mode,
+                dialog: dialog,
+                page: page,
+                end_page: page,
+                top: None,
+                end_top: None,
+                revised: false,
+                source: Vec::new(),
+            };
+
+            // the marked dialog is spoken at the same time as the previous one
+            let previous_dialog = script_parts.iter().rposition(|part| match part {
+                &ScriptPart::Separator => false,
+                _ => true,
+            }).and_then(|position| match script_parts[position] {
+                ScriptPart::ScenePart(ScenePart::Dialog(_)) => Some(position),
+                _ => None,
+            });
+
+            match previous_dialog {
+                Some(position) if is_dual => {
+                    if let ScriptPart::ScenePart(ScenePart::Dialog(left)) = script_parts.remove(position) {
+                        script_parts.push(ScriptPart::ScenePart(ScenePart::DualDialog {
+                            left: left,
+                            right: dialog,
+                        }));
+                    }
+                }
+                _ => script_parts.push(ScriptPart::ScenePart(ScenePart::Dialog(dialog))),
+            }
+        } else {
+            // ensure the last script part is a direction
+            if let Some(&ScriptPart::ScenePart(ScenePart::Direction{..})) = script_parts.last() {
+            } else {
+                script_parts.push(ScriptPart::ScenePart(
+                    ScenePart::Direction {
+                        direction: String::new(),
+                        page: page,
+                        end_page: page,
+                        top: None,
+                        end_top: None,
+                        revised: false,
+                        source: Vec::new(),
+                        emphasis: Vec::new(),
+                    }));
+            }
+
+            if let Some(&mut ScriptPart::ScenePart(ScenePart::Direction{ref mut direction, ..})) = script_parts.last_mut() {
+                if direction.len() > 0 {
+                    direction.push(' ');
+                }
+                direction.push_str(extract_action(line));
+            }
+        }
+
+        index += 1;
+    }
+
+    script_parts
+}
+
+/// Appends a line of a dialog block either as parenthetical or as dialog.
+fn append_dialog_line(dialog: &mut Vec<DialogPart>, line: &str) {
+    // parentheticals can span several lines until the closing bracket
+    let in_parenthetical = match dialog.last() {
+        Some(&DialogPart::Direction(ref direction, _, _)) => !direction.ends_with(')'),
+        _ => false,
+    };
+
+    if line.starts_with('(') || in_parenthetical {
+        if !in_parenthetical {
+            dialog.push(DialogPart::Direction(String::new(), Vec::new(), Vec::new()));
+        }
+
+        if let Some(&mut DialogPart::Direction(ref mut direction, _, _)) = dialog.last_mut() {
+            if direction.len() > 0 {
+                direction.push(' ');
+            }
+            direction.push_str(line);
+        }
+    } else {
+        if let Some(&DialogPart::Dialog(_, _, _)) = dialog.last() {
+        } else {
+            dialog.push(DialogPart::Dialog(String::new(), Vec::new(), Vec::new()));
+        }
+
+        if let Some(&mut DialogPart::Dialog(ref mut dialog, _, _)) = dialog.last_mut() {
+            if dialog.len() > 0 {
+                dialog.push(' ');
+            }
+            dialog.push_str(line);
+        }
+    }
+}
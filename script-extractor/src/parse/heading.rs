@@ -0,0 +1,216 @@
+//! Recognizing scene headings like `INT. KITCHEN - NIGHT`.
+//!
+//! A scene heading starts with a prefix giving the `LocationType`, like
+//! `INT.` or `EXT.`. The known prefixes are configured as `HeadingRules`,
+//! which by default cover the prefixes commonly used in scripts.
+
+/// A prefix of a scene heading and the `LocationType` it stands for.
+#[derive(Debug, Clone)]
+pub struct HeadingRule {
+    /// The prefix without periods, like `INT` or `INT/EXT`
+    pub prefix: String,
+    pub kind: LocationType,
+}
+
+/// The prefixes which start a scene heading.
+///
+/// Prefixes are compared without their periods, so the rule `INT/EXT` also
+/// matches `INT./EXT.`, and the name may directly follow the period, like
+/// in `INT.KITCHEN`. A prefix with a period is matched regardless of its
+/// case, one without a period has to be uppercase, so that a direction like
+/// `Est 1850 the town ...` is not taken for a heading. Formats which mark
+/// their headings, like Fountain, can `ignore_case` completely.
+///
+/// Headings can be numbered, like `42 INT. KITCHEN - NIGHT 42`.
+///
+/// # Examples
+///
+/// ```
+/// # use script_extractor::LocationType::*;
+/// # use script_extractor::parse::heading::HeadingRules;
+/// let mut rules: HeadingRules = Default::default();
+///
+/// let headings = [("INT. KITCHEN - NIGHT", Internal, "KITCHEN - NIGHT", None),
+///                 ("EXT. FOREST", External, "FOREST", None),
+///                 ("INT./EXT. CAR - MOVING", InternalExternal, "CAR - MOVING", None),
+///                 ("INT/EXT CAR", InternalExternal, "CAR", None),
+///                 ("EXT./INT. BARN", InternalExternal, "BARN", None),
+///                 ("I/E PORCH - DAY", InternalExternal, "PORCH - DAY", None),
+///                 ("E/I PORCH", InternalExternal, "PORCH", None),
+///                 ("EST. CITY SKYLINE", Establishing, "CITY SKYLINE", None),
+///                 ("INT KITCHEN", Internal, "KITCHEN", None),
+///                 ("INT.KITCHEN", Internal, "KITCHEN", None),
+///                 ("Int. Kitchen - Day", Internal, "Kitchen - Day", None),
+///                 ("int. kitchen", Internal, "kitchen", None),
+///                 ("i/e. porch", InternalExternal, "porch", None),
+///                 ("42 INT. KITCHEN - NIGHT 42", Internal, "KITCHEN - NIGHT", Some("42")),
+///                 ("12A. EXT. STREET 12A.", External, "STREET", Some("12A")),
+///                 ("7 INT. ROOM 101", Internal, "ROOM 101", Some("7"))];
+///
+/// for &(heading, ref kind, name, number) in headings.iter() {
+///     let (heading, parsed_number) = rules.parse_heading(heading).unwrap();
+///     let location = rules.extract_location(&heading);
+///     assert_eq!(&location.kind, kind);
+///     assert_eq!(location.name, name);
+///     assert_eq!(parsed_number.as_ref().map(|n| n.as_ref()), number);
+/// }
+///
+/// let directions = ["Interior designers hate him.", "Int kitchen", "est city skyline",
+///                   "INTO THE WOODS", "EXTERMINATE!", "INT.", "42", "ROOM 101"];
+///
+/// for direction in directions.iter() {
+///     assert!(!rules.is_heading(direction));
+/// }
+/// assert_eq!(rules.extract_location("est city skyline").kind, Undefined);
+///
+/// rules.ignore_case = true;
+/// assert_eq!(rules.extract_location("int kitchen - day").kind, Internal);
+/// assert_eq!(rules.extract_location("est city skyline").kind, Establishing);
+/// assert!(!rules.is_heading("Interior designers hate him."));
+/// ```
+#[derive(Debug, Clone)]
+pub struct HeadingRules {
+    pub rules: Vec<HeadingRule>,
+    /// Whether prefixes without a period are matched regardless of their
+    /// case, too
+    pub ignore_case: bool,
+}
+
+impl HeadingRules {
+    /// Creates rules without any prefixes.
+    pub fn new() -> HeadingRules {
+        HeadingRules {
+            rules: Vec::new(),
+            ignore_case: false,
+        }
+    }
+
+    /// Adds a prefix, replacing the rule of the same prefix if it exists.
+    pub fn add(&mut self, prefix: &str, kind: LocationType) {
+        let prefix = normalize_prefix(prefix);
+        self.rules.retain(|rule| rule.prefix != prefix);
+        self.rules.push(HeadingRule {
+            prefix: prefix,
+            kind: kind,
+        });
+    }
+
+    /// Checks if the given line is a scene heading.
+    pub fn is_heading(&self, line: &str) -> bool {
+        self.parse_heading(line).is_some()
+    }
+
+    /// Splits the scene numbers off a scene heading.
+    ///
+    /// A number at the end of the heading is only removed together with the
+    /// same number at its start, as it could also be part of the name.
+    /// Returns `None` if the line is no scene heading.
+    pub fn parse_heading(&self, line: &str) -> Option<(String, Option<String>)> {
+        let (heading, number) = match SCENE_NUMBER.captures(line.trim()) {
+            Some(captures) => {
+                let number = captures.at(1).unwrap();
+                let rest = captures.at(2).unwrap();
+
+                let mut words = rest.rsplitn(2, char::is_whitespace);
+                let heading = match (words.next(), words.next()) {
+                    (Some(last), Some(init)) if last.trim_end_matches('.') == number => init.trim_end(),
+                    _ => rest,
+                };
+                (heading, Some(number.to_string()))
+            }
+            None => (line.trim(), None),
+        };
+
+        match self.split_prefix(heading) {
+            Some((_, name)) if name.len() > 0 => Some((heading.to_string(), number)),
+            _ => None,
+        }
+    }
+
+    /// Parses a scene heading without scene numbers into a `Location`.
+    ///
+    /// Headings without a known prefix become the name of a location of
+    /// type `Undefined`. The name is split at dashes into the primary place,
+    /// the sub-locations and the time of day, if the last part is one.
+    pub fn extract_location(&self, heading: &str) -> Location {
+        let mut location: Location = Default::default();
+
+        match self.split_prefix(heading.trim()) {
+            Some((kind, name)) => {
+                location.kind = kind;
+                location.name = name.to_string();
+            }
+            None => location.name = heading.trim().to_string(),
+        }
+
+        let mut parts: Vec<String> = SEPARATOR.split(&location.name)
+                                          .map(|part| part.trim().to_string())
+                                          .collect();
+
+        if parts.len() > 1 {
+            location.time_of_day = parts.last().and_then(|part| TimeOfDay::from_text(part));
+            if location.time_of_day.is_some() {
+                parts.pop();
+            }
+        }
+
+        let mut parts = parts.into_iter();
+        location.primary_place = parts.next().unwrap_or(String::new());
+        location.sub_locations = parts.collect();
+
+        location
+    }
+
+    /// Splits the prefix off the given heading.
+    ///
+    /// The prefix is the first word of the heading, or the part of it up to
+    /// a period if the name directly follows the prefix. Returns the
+    /// `LocationType` of the prefix and the rest of the heading.
+    fn split_prefix<'a>(&self, heading: &'a str) -> Option<(LocationType, &'a str)> {
+        let word = heading.split_whitespace().next().unwrap_or("");
+        let prefixes = Some(word.len()).into_iter()
+                                       .chain(word.rmatch_indices('.').map(|(i, _)| i + 1))
+                                       .map(|end| &word[..end]);
+
+        prefixes.filter(|prefix| self.ignore_case || prefix.contains('.') || prefix.to_uppercase() == *prefix)
+                .filter_map(|prefix| {
+                    self.find_rule(prefix).map(|rule| (rule.kind.clone(), heading[prefix.len()..].trim()))
+                })
+                .next()
+    }
+
+    fn find_rule(&self, prefix: &str) -> Option<&HeadingRule> {
+        let normalized = normalize_prefix(prefix);
+        self.rules.iter().find(|rule| rule.prefix == normalized)
+    }
+}
+
+/// The default rules know the prefixes `INT`, `EXT`, `EST` and the
+/// combinations `INT/EXT`, `EXT/INT`, `I/E` and `E/I`.
+impl Default for HeadingRules {
+    fn default() -> HeadingRules {
+        let mut rules = HeadingRules::new();
+        rules.add("INT", LocationType::Internal);
+        rules.add("EXT", LocationType::External);
+        rules.add("INT/EXT", LocationType::InternalExternal);
+        rules.add("EXT/INT", LocationType::InternalExternal);
+        rules.add("I/E", LocationType::InternalExternal);
+        rules.add("E/I", LocationType::InternalExternal);
+        rules.add("EST", LocationType::Establishing);
+        rules
+    }
+}
+
+
+use ::{Location, LocationType, TimeOfDay};
+use regex::Regex;
+
+lazy_static! {
+    static ref SCENE_NUMBER: Regex = Regex::new(r"^(\d+[A-Z]{0,2})\.?\s+(.+)$").unwrap();
+    static ref SEPARATOR: Regex = Regex::new(r"\s+(?:--?|–|—)\s+").unwrap();
+}
+
+/// Removes the periods from a prefix and converts it to uppercase.
+fn normalize_prefix(prefix: &str) -> String {
+    prefix.chars().filter(|&c| c != '.').flat_map(|c| c.to_uppercase()).collect()
+}
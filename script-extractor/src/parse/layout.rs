@@ -0,0 +1,180 @@
+//! Describing the page layout of scripts extracted from pdfs.
+//!
+//! The poppler xml-format only contains the position of each line, so the
+//! kind of a line is determined by the column it starts in. The columns are
+//! detected heuristically by `parse_script`, but can also be given
+//! explicitly as a `LayoutProfile`, which can be stored as `toml` or `json`.
+//!
+//! # Example
+//!
+//! ```toml
+//! line_height = 15
+//! section_gap = 18
+//!
+//! [dialog]
+//! position = 252
+//! tolerance = 0
+//!
+//! [direction]
+//! position = 108
+//! tolerance = 0
+//!
+//! [speaker]
+//! position = 324
+//! tolerance = 0
+//!
+//! [speaker_direction]
+//! position = 288
+//! tolerance = 0
+//! ```
+
+/// A column of the page, identified by the `left` position of its lines.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Column {
+    pub position: i32,
+    /// The maximum distance of a line from `position` to still be part of
+    /// the column
+    #[serde(default)]
+    pub tolerance: i32,
+}
+
+/// The kinds of lines that can be distinguished by their column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnKind {
+    Direction,
+    Speaker,
+    SpeakerDirection,
+    Dialog,
+    Transition,
+}
+
+/// The columns and spacing of a script.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayoutProfile {
+    pub direction: Column,
+    pub dialog: Column,
+    /// The column of the parentheticals within dialogs
+    pub speaker_direction: Option<Column>,
+    pub speaker: Column,
+    /// The column of right-aligned or centered transitions like `CUT TO:`
+    pub transition: Option<Column>,
+    /// The minimum vertical distance between two lines which starts a new
+    /// section
+    #[serde(default = "default_section_gap")]
+    pub section_gap: i32,
+    /// The most common vertical distance between two lines
+    #[serde(default = "default_line_height")]
+    pub line_height: i32,
+}
+
+impl Column {
+    /// Creates a column at the given position without any tolerance.
+    pub fn new(position: i32) -> Column {
+        Column {
+            position: position,
+            tolerance: 0,
+        }
+    }
+
+    /// Checks if a line starting at `left` belongs to the column.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use script_extractor::parse::layout::Column;
+    /// let column = Column { position: 108, tolerance: 2 };
+    /// assert!(column.contains(110));
+    /// assert!(!column.contains(111));
+    /// ```
+    pub fn contains(&self, left: i32) -> bool {
+        (left - self.position).abs() <= self.tolerance
+    }
+}
+
+impl LayoutProfile {
+    /// Returns the kind of the column a line starting at `left` belongs to.
+    ///
+    /// If several columns contain the line, it is assigned to the nearest
+    /// one. Equally near columns are preferred in the order direction,
+    /// speaker, speaker direction, dialog and transition.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use script_extractor::parse::layout::{Column, ColumnKind, LayoutProfile};
+    /// let layout = LayoutProfile {
+    ///     direction: Column { position: 108, tolerance: 2 },
+    ///     dialog: Column { position: 180, tolerance: 40 },
+    ///     speaker: Column { position: 252, tolerance: 40 },
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(layout.column_at(107), Some(ColumnKind::Direction));
+    /// assert_eq!(layout.column_at(214), Some(ColumnKind::Dialog));
+    /// assert_eq!(layout.column_at(230), Some(ColumnKind::Speaker));
+    /// assert_eq!(layout.column_at(300), None);
+    /// ```
+    pub fn column_at(&self, left: i32) -> Option<ColumnKind> {
+        let columns = [(ColumnKind::Direction, Some(self.direction)),
+                       (ColumnKind::Speaker, Some(self.speaker)),
+                       (ColumnKind::SpeakerDirection, self.speaker_direction),
+                       (ColumnKind::Dialog, Some(self.dialog)),
+                       (ColumnKind::Transition, self.transition)];
+
+        columns.iter()
+               .filter_map(|&(kind, column)| column.map(|column| (kind, column)))
+               .filter(|&(_, column)| column.contains(left))
+               .min_by_key(|&(_, column)| (left - column.position).abs())
+               .map(|(kind, _)| kind)
+    }
+
+    /// Reads a `LayoutProfile` from `toml`.
+    ///
+    /// The tolerances, `section_gap` and `line_height` are optional.
+    pub fn from_toml(input: &str) -> Result<LayoutProfile, ParseError> {
+        toml::from_str(input).map_err(|error| {
+            ParseError::InvalidStructure { message: error.to_string() }
+        })
+    }
+
+    /// Reads a `LayoutProfile` from `json`.
+    ///
+    /// The tolerances, `section_gap` and `line_height` are optional.
+    pub fn from_json(input: &str) -> Result<LayoutProfile, ParseError> {
+        Ok(try!(serde_json::from_str(input)))
+    }
+
+    /// Serializes the `LayoutProfile` into `toml`.
+    pub fn to_toml(&self) -> String {
+        // a table writes the numbers before the columns, as required by toml
+        toml::Value::try_from(self).expect("a layout consists of numbers only").to_string()
+    }
+}
+
+/// The default layout has no columns, but the section gap used by the
+/// detection.
+impl Default for LayoutProfile {
+    fn default() -> LayoutProfile {
+        LayoutProfile {
+            direction: Default::default(),
+            dialog: Default::default(),
+            speaker_direction: None,
+            speaker: Default::default(),
+            transition: None,
+            section_gap: 18,
+            line_height: 0,
+        }
+    }
+}
+
+
+use parse::ParseError;
+use serde_json;
+use toml;
+
+fn default_section_gap() -> i32 {
+    LayoutProfile::default().section_gap
+}
+
+fn default_line_height() -> i32 {
+    LayoutProfile::default().line_height
+}
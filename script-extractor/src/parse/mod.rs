@@ -0,0 +1,1500 @@
+//! Parsing scripts into `Script`s.
+//!
+//! This module provides functions to parse scripts which have been extracted
+//! from pdfs into `Script`s. Scripts in other formats can be parsed using
+//! the submodules.
+
+pub mod diagnostics;
+pub mod fdx;
+pub mod fountain;
+pub mod heading;
+pub mod layout;
+pub mod title_page;
+
+/// Parses the given script into a `Script`.
+///
+/// Reads the parsed pdf of a script in the poppler xml-format
+/// (`pdftohtml --xml`) from `reader` and parses it into a `Script`. If the
+/// first page is a title page, it is parsed into the `ScriptMetadata`.
+///
+/// Returns a `ParseError` if the input is not valid xml, contains
+/// non-numeric positions or if its layout cannot be detected.
+///
+/// # Examples
+///
+/// A direction continuing on the next page is kept as one part:
+///
+/// ```
+/// # use script_extractor::ScenePart;
+/// # use script_extractor::parse::parse_script;
+/// let pages = r#"<pdf2xml>
+///     <page number="1">
+///         <text top="100" left="108" width="300" height="15">EXT. SNOWY LANDSCAPE - DAY</text>
+///         <text top="130" left="252" width="100" height="15">SINTEL</text>
+///         <text top="145" left="180" width="300" height="15">Who are you?</text>
+///         <text top="175" left="108" width="500" height="15">Swirls of snow obscure the rocky</text>
+///         <text top="190" left="108" width="500" height="15">formations of a mountain, as five</text>
+///     </page>
+///     <page number="2">
+///         <text top="100" left="108" width="500" height="15">ragged men attack a young girl.</text>
+///         <text top="130" left="252" width="100" height="15">SHAMAN</text>
+///         <text top="145" left="180" width="300" height="15">Run!</text>
+///     </page>
+/// </pdf2xml>"#;
+///
+/// let script = parse_script(&mut pages.as_bytes()).unwrap();
+/// let parts = &script.scenes[0][0].parts;
+///
+/// assert_eq!(parts.len(), 3);
+/// match parts[1] {
+///     ScenePart::Direction { ref direction, page, end_page, .. } => {
+///         assert_eq!(direction, "Swirls of snow obscure the rocky formations of a mountain, \
+///                                as five ragged men attack a young girl.");
+///         assert_eq!((page, end_page), (1, 2));
+///     }
+///     _ => panic!("expected a direction"),
+/// }
+/// ```
+pub fn parse_script(reader: &mut Read) -> Result<Script, ParseError> {
+    parse_script_with_layout(reader, &Default::default(), |detected_layout| detected_layout).0
+}
+
+/// Parses the given script into a `Script` using custom rules and layout.
+///
+/// Works like `parse_script`, but recognizes the parts of the script using
+/// the given `ParseRules` and passes the detected `LayoutProfile` (or the
+/// error if the layout could not be detected) to `choose_layout`, which
+/// returns the layout used for parsing. This allows to replace or adjust the
+/// detected layout for scripts with unusual layouts.
+///
+/// Page artifacts like page numbers, running headers, `(MORE)` and
+/// `(CONTINUED)` are removed before parsing, and dialogs which are split
+/// across a page break are merged again.
+///
+/// Returns the `Script` together with the `Diagnostics` of parsing it, which
+/// contain the layout used, the removed artifacts and the lines which did not
+/// match any column of the layout and were skipped. The `Diagnostics` are
+/// returned even if parsing fails, containing everything found up to the
+/// error.
+///
+/// # Examples
+///
+/// ```
+/// # use script_extractor::{Dialog, DialogPart, ScenePart};
+/// # use script_extractor::parse::{self, parse_script_with_layout};
+/// let pages = r#"<pdf2xml>
+///     <page number="1">
+///         <text top="100" left="108" width="300" height="15">INT. CAVE - NIGHT</text>
+///         <text top="130" left="108" width="500" height="15">Fire crackles.</text>
+///         <text top="160" left="252" width="100" height="15">SHAMAN</text>
+///         <text top="175" left="180" width="300" height="15">Rest now.</text>
+///         <text top="190" left="180" width="300" height="15">You are safe</text>
+///         <text top="205" left="252" width="100" height="15">(MORE)</text>
+///         <text top="235" left="450" width="100" height="15">(CONTINUED)</text>
+///         <text top="1100" left="504" width="20" height="15">1.</text>
+///     </page>
+///     <page number="2">
+///         <text top="50" left="504" width="20" height="15">2.</text>
+///         <text top="70" left="108" width="100" height="15">CONTINUED:</text>
+///         <text top="100" left="252" width="100" height="15">SHAMAN (CONT'D)</text>
+///         <text top="115" left="180" width="300" height="15">here with me.</text>
+///         <text top="145" left="108" width="500" height="15">She sleeps.</text>
+///     </page>
+/// </pdf2xml>"#;
+///
+/// let rules: parse::ParseRules = Default::default();
+/// let (script, diagnostics) = parse_script_with_layout(&mut pages.as_bytes(), &rules, |layout| layout);
+/// let parts = &script.unwrap().scenes[0][0].parts;
+///
+/// assert_eq!(parts.len(), 3);
+/// match parts[1] {
+///     ScenePart::Dialog(Dialog { ref speaker, ref dialog, page, end_page, .. }) => {
+///         assert_eq!(speaker, "SHAMAN");
+///         assert_eq!((page, end_page), (1, 2));
+///         match dialog[..] {
+///             [DialogPart::Dialog(ref text, _, _)] => assert_eq!(text, "Rest now. You are safe here with me."),
+///             _ => panic!("expected a single line of dialog"),
+///         }
+///     }
+///     _ => panic!("expected a dialog"),
+/// }
+///
+/// let artifacts: Vec<&str> = diagnostics.artifacts.iter().map(|line| line.text.as_ref()).collect();
+/// assert_eq!(artifacts, ["(MORE)", "(CONTINUED)", "1.", "2.", "CONTINUED:", "SHAMAN (CONT'D)"]);
+/// ```
+pub fn parse_script_with_layout<F>(reader: &mut Read, rules: &ParseRules, choose_layout: F)
+    -> (Result<Script, ParseError>, Diagnostics)
+    where F: FnOnce(Result<LayoutProfile, ParseError>) -> Result<LayoutProfile, ParseError> {
+    let mut diagnostics: Diagnostics = Default::default();
+    let script = parse_script_with_diagnostics(reader, rules, choose_layout, &mut diagnostics);
+    if let Err(ref e) = script {
+        diagnostics.error = Some(e.to_string());
+    }
+
+    (script, diagnostics)
+}
+
+/// The rules used to recognize the parts of a script.
+#[derive(Debug, Clone)]
+pub struct ParseRules {
+    pub headings: HeadingRules,
+    /// The kinds of transitions which start a new `Scene`
+    pub scene_transitions: Vec<TransitionKind>,
+    /// Whether bold uppercase lines standing alone in the direction column
+    /// are scene headings, even without a known prefix like `INT.`
+    pub bold_headings: bool,
+}
+
+/// By default, all transitions to another shot start a new `Scene`, but
+/// not `FADE IN:`, `FADE OUT.` and `INTERCUT WITH:`. Bold lines are no
+/// scene headings by default, as they are also used for emphasized action.
+impl Default for ParseRules {
+    fn default() -> ParseRules {
+        ParseRules {
+            headings: Default::default(),
+            scene_transitions: vec![TransitionKind::Cut,
+                                    TransitionKind::SmashCut,
+                                    TransitionKind::MatchCut,
+                                    TransitionKind::Dissolve,
+                                    TransitionKind::Other],
+            bold_headings: false,
+        }
+    }
+}
+
+/// Parses a scene heading into a `Location` using the default `HeadingRules`.
+///
+/// The name of the heading is split at dashes into the primary place, the
+/// sub-locations and the time of day, if the last part is one.
+///
+/// # Examples
+///
+/// ```
+/// # use script_extractor::TimeOfDay;
+/// # use script_extractor::parse::extract_location;
+/// let location = extract_location("INT. HOUSE - BEDROOM - CONTINUOUS");
+/// assert_eq!(location.name, "HOUSE - BEDROOM - CONTINUOUS");
+/// assert_eq!(location.primary_place, "HOUSE");
+/// assert_eq!(location.sub_locations, vec!["BEDROOM"]);
+/// assert_eq!(location.time_of_day, Some(TimeOfDay::Continuous));
+///
+/// let location = extract_location("EXT. SNOWY LANDSCAPE");
+/// assert_eq!(location.primary_place, "SNOWY LANDSCAPE");
+/// assert_eq!(location.time_of_day, None);
+/// ```
+pub fn extract_location(heading: &str) -> Location {
+    HeadingRules::default().extract_location(heading)
+}
+
+/// Splits the speaker of a `Dialog` into the characters speaking together.
+///
+/// The names are separated by `&`, `AND`, `/`, `+` or commas. Cues for
+/// unnamed groups like `ALL`, `CROWD` or `EVERYONE`, and single names
+/// speaking `(IN UNISON)` or `(TOGETHER)`, are `Group`s.
+///
+/// # Examples
+///
+/// ```
+/// # use script_extractor::{DialogMode, Speaker};
+/// # use script_extractor::parse::split_speakers;
+/// assert_eq!(split_speakers("WOODY & BUZZ", &[]),
+///            vec![Speaker::Character("WOODY".to_string()), Speaker::Character("BUZZ".to_string())]);
+/// assert_eq!(split_speakers("Sandy and Randy", &[]),
+///            vec![Speaker::Character("Sandy".to_string()), Speaker::Character("Randy".to_string())]);
+/// assert_eq!(split_speakers("THE CROWD", &[]), vec![Speaker::Group("THE CROWD".to_string())]);
+///
+/// let unison = [DialogMode::from_extension("IN UNISON")];
+/// assert_eq!(split_speakers("ALIENS", &unison), vec![Speaker::Group("ALIENS".to_string())]);
+/// ```
+pub fn split_speakers(speaker: &str, mode: &[DialogMode]) -> Vec<Speaker> {
+    let names: Vec<&str> = SPEAKER_SEPARATOR.split(speaker)
+                                    .map(|name| name.trim())
+                                    .filter(|name| name.len() > 0)
+                                    .collect();
+    let in_unison = mode.iter().any(|mode| match mode {
+        &DialogMode::Other(ref extension) => {
+            let extension = extension.to_uppercase();
+            extension.contains("UNISON") || extension == "TOGETHER"
+        }
+        _ => false,
+    });
+
+    names.iter().map(|name| {
+        if is_group_name(name) || (in_unison && names.len() == 1) {
+            Speaker::Group(name.to_string())
+        } else {
+            Speaker::Character(name.to_string())
+        }
+    }).collect()
+}
+
+/// The errors that can occur while parsing a script.
+#[derive(Debug, Clone)]
+pub enum ParseError {
+    /// The input could not be read
+    Io {
+        message: String,
+    },
+    /// The input is not well-formed xml
+    Xml {
+        /// The line of the error, counting from 1
+        line: u64,
+        /// The column of the error, counting from 1
+        column: u64,
+        message: String,
+    },
+    /// The input is not well-formed json
+    Json {
+        /// The line of the error, counting from 1
+        line: u64,
+        /// The column of the error, counting from 1
+        column: u64,
+        message: String,
+    },
+    /// The input is well-formed, but does not have the expected structure
+    InvalidStructure {
+        message: String,
+    },
+    /// An attribute which should be numeric could not be parsed
+    InvalidAttribute {
+        /// The page on which the attribute was found
+        page: u32,
+        /// The element carrying the attribute (like `text` or `page`)
+        element: String,
+        attribute: String,
+        value: String,
+    },
+    /// The script does not use the expected three column layout
+    UnknownLayout {
+        /// The histogram of `left` positions and their number of uses,
+        /// sorted by uses descending
+        position_uses: Vec<(i32, u32)>,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &ParseError::Io { ref message } => {
+                write!(f, "Error reading input: {}", message)
+            }
+            &ParseError::Xml { line, column, ref message } => {
+                write!(f, "Error parsing xml at {}:{}: {}", line, column, message)
+            }
+            &ParseError::Json { line, column, ref message } => {
+                write!(f, "Error parsing json at {}:{}: {}", line, column, message)
+            }
+            &ParseError::InvalidStructure { ref message } => {
+                write!(f, "Invalid script structure: {}", message)
+            }
+            &ParseError::InvalidAttribute { page, ref element, ref attribute, ref value } => {
+                write!(f, "Invalid value '{}' for attribute '{}' of <{}> on page {}",
+                       value, attribute, element, page)
+            }
+            &ParseError::UnknownLayout { ref position_uses } => {
+                try!(write!(f, "Script uses strange layout, found columns:"));
+                for &(position, uses) in position_uses.iter() {
+                    try!(write!(f, " {} ({}x)", position, uses));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Converts errors of the xml parser into `ParseError::Xml`.
+impl From<XmlError> for ParseError {
+    fn from(error: XmlError) -> ParseError {
+        let position = error.position();
+        ParseError::Xml {
+            line: position.row + 1,
+            column: position.column + 1,
+            message: error.msg().to_string(),
+        }
+    }
+}
+
+/// Converts errors of the json parser into `ParseError::Json`,
+/// `ParseError::InvalidStructure` for well-formed json not describing a
+/// script, or `ParseError::Io`.
+impl From<JsonError> for ParseError {
+    fn from(error: JsonError) -> ParseError {
+        match error.classify() {
+            JsonCategory::Syntax | JsonCategory::Eof => {
+                // the position is kept separately
+                let position = format!(" at line {} column {}", error.line(), error.column());
+                ParseError::Json {
+                    line: error.line() as u64,
+                    column: error.column() as u64,
+                    message: error.to_string().trim_end_matches(&position[..]).to_string(),
+                }
+            }
+            JsonCategory::Data => ParseError::InvalidStructure { message: error.to_string() },
+            JsonCategory::Io => ParseError::Io { message: error.to_string() },
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        match self {
+            &ParseError::Io { .. } => "io error",
+            &ParseError::Xml { .. } => "invalid xml",
+            &ParseError::Json { .. } => "invalid json",
+            &ParseError::InvalidStructure { .. } => "invalid structure",
+            &ParseError::InvalidAttribute { .. } => "invalid attribute",
+            &ParseError::UnknownLayout { .. } => "unknown layout",
+        }
+    }
+}
+
+
+use ::{Dialog, DialogMode, DialogPart, Emphasis, EmphasisStyle, Location, Scene, ScenePart, Script, ScriptMetadata, SourceSpan,
+     Speaker, TransitionKind};
+use self::diagnostics::{DetectedColumn, Diagnostics, SectionBreak, UnmatchedLine};
+use self::heading::HeadingRules;
+use self::layout::{Column, ColumnKind, LayoutProfile};
+use regex::Regex;
+use serde_json::Error as JsonError;
+use serde_json::error::Category as JsonCategory;
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+use xml::EventReader;
+use xml::attribute::OwnedAttribute;
+use xml::common::Position;
+use xml::reader::Error as XmlError;
+use xml::reader::XmlEvent;
+
+fn parse_script_with_diagnostics<F>(reader: &mut Read, rules: &ParseRules, choose_layout: F,
+                                   diagnostics: &mut Diagnostics) -> Result<Script, ParseError>
+    where F: FnOnce(Result<LayoutProfile, ParseError>) -> Result<LayoutProfile, ParseError> {
+    let lines = try!(read_script(reader));
+    let (metadata, lines) = extract_title_page(lines, rules);
+
+    let detected_layout = analyze_script(&lines, diagnostics);
+    diagnostics.detected_layout = detected_layout.as_ref().ok().cloned();
+
+    let layout = try!(choose_layout(detected_layout));
+    diagnostics.layout = layout.clone();
+
+    let (lines, continued_lines) = remove_artifacts(&layout, rules, lines, diagnostics);
+    let parts = extract_script_parts(&layout, rules, &lines, &continued_lines, diagnostics);
+
+    Ok(Script {
+        metadata: metadata,
+        scenes: extract_scenes(&parts, rules),
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+struct LineAttributes {
+    top: i32,
+    left: i32,
+    width: i32,
+    height: i32,
+    page: u32,
+    /// The index of the `<text>` element in the document
+    index: usize,
+    /// The bold and italic ranges of the line, given by its font or by
+    /// inline `<b>` and `<i>` elements
+    emphasis: Vec<Emphasis>,
+}
+
+impl LineAttributes {
+    fn source_span(&self) -> SourceSpan {
+        SourceSpan::new(self.page, self.top, self.left, self.top + self.height, self.left + self.width, self.index)
+    }
+}
+
+fn read_attributes(attr_list: &Vec<OwnedAttribute>, page: u32) -> Result<LineAttributes, ParseError> {
+    let mut attributes : LineAttributes = Default::default();
+
+    for attr in attr_list {
+        match attr.name.local_name.as_ref() {
+            "top" => attributes.top = try!(parse_attribute(attr, "text", page)),
+            "left" => attributes.left = try!(parse_attribute(attr, "text", page)),
+            "width" => attributes.width = try!(parse_attribute(attr, "text", page)),
+            "height" => attributes.height = try!(parse_attribute(attr, "text", page)),
+            _ => {}
+        }
+    }
+
+    Ok(attributes)
+}
+
+/// Parse the value of a numeric attribute of the given element.
+fn parse_attribute<T: FromStr>(attr: &OwnedAttribute, element: &str, page: u32) -> Result<T, ParseError> {
+    attr.value.parse().map_err(|_| ParseError::InvalidAttribute {
+        page: page,
+        element: element.to_string(),
+        attribute: attr.name.local_name.clone(),
+        value: attr.value.clone(),
+    })
+}
+
+fn read_script(reader: &mut Read) -> Result<Vec<(LineAttributes, String)>, ParseError> {
+    let mut lines: Vec<(LineAttributes, String)> = Vec::new();
+
+    // the styles of the fonts by their id
+    let mut font_styles: HashMap<String, Vec<EmphasisStyle>> = HashMap::new();
+
+    // states for the streaming xml parsing
+    let mut current_line_attributes: LineAttributes = Default::default();
+    let mut current_line_styles: Vec<EmphasisStyle> = Vec::new();
+    let mut current_inline_styles: Vec<EmphasisStyle> = Vec::new();
+    let mut current_text_buffer = String::new();
+    let mut current_page_number = 0;
+
+    let parser = EventReader::new(reader);
+    for event in parser {
+        match event {
+            Ok(XmlEvent::StartElement { name, attributes, .. }) => {
+                match name.local_name.as_ref() {
+                    "text" => {
+                        current_line_attributes = try!(read_attributes(&attributes, current_page_number));
+                        current_line_styles = attributes.iter()
+                                                        .find(|attr| attr.name.local_name == "font")
+                                                        .and_then(|font| font_styles.get(&font.value))
+                                                        .cloned()
+                                                        .unwrap_or(Vec::new());
+                        current_inline_styles.clear();
+
+                        current_text_buffer.clear();
+                    }
+                    "fontspec" => {
+                        let id = attributes.iter().find(|attr| attr.name.local_name == "id");
+                        let family = attributes.iter().find(|attr| attr.name.local_name == "family");
+                        if let (Some(id), Some(family)) = (id, family) {
+                            font_styles.insert(id.value.clone(), extract_font_styles(&family.value));
+                        }
+                    }
+                    "b" => current_inline_styles.push(EmphasisStyle::Bold),
+                    "i" => current_inline_styles.push(EmphasisStyle::Italic),
+                    "page" => {
+                        for attr in attributes {
+                            if "number" == attr.name.local_name {
+                                current_page_number = try!(parse_attribute(&attr, "page", current_page_number));
+                                break;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(XmlEvent::Characters(text)) | Ok(XmlEvent::Whitespace(text)) => {
+                // the whitespace is collapsed at the end of the line
+                let start = current_text_buffer.chars().count();
+                current_text_buffer.push_str(&text);
+                let end = current_text_buffer.chars().count();
+
+                for &style in current_inline_styles.iter() {
+                    current_line_attributes.emphasis.push(Emphasis { style: style, start: start, end: end });
+                }
+            }
+            Ok(XmlEvent::EndElement { name, .. }) => {
+                match name.local_name.as_ref() {
+                    "text" => {
+                        let length = current_text_buffer.chars().count();
+                        for &style in current_line_styles.iter() {
+                            current_line_attributes.emphasis.push(Emphasis { style: style, start: 0, end: length });
+                        }
+                        let text = collapse_whitespace(&current_text_buffer, &mut current_line_attributes.emphasis);
+                        join_emphasis(&mut current_line_attributes.emphasis, &text);
+
+                        current_line_attributes.page = current_page_number;
+                        current_line_attributes.index = lines.len();
+                        lines.push((current_line_attributes.clone(), text));
+                    }
+                    "b" | "i" => {
+                        current_inline_styles.pop();
+                    }
+                    _ => {}
+                }
+            }
+            Ok(_) => {},
+            Err(e) => return Err(ParseError::from(e)),
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Derives the styles of a font from its family, like `Courier-Bold`.
+///
+/// Fonts have no underlined variants, see `EmphasisStyle`.
+fn extract_font_styles(family: &str) -> Vec<EmphasisStyle> {
+    let family = family.to_lowercase();
+
+    let mut styles = Vec::new();
+    if family.contains("bold") || family.contains("black") || family.contains("heavy") {
+        styles.push(EmphasisStyle::Bold);
+    }
+    if family.contains("italic") || family.contains("oblique") {
+        styles.push(EmphasisStyle::Italic);
+    }
+
+    styles
+}
+
+/// Collapses the whitespace of a line and trims it, moving its `Emphasis`
+/// along.
+fn collapse_whitespace(text: &str, emphasis: &mut Vec<Emphasis>) -> String {
+    let mut collapsed = String::new();
+    let mut length = 0;
+    let mut has_space = false;
+
+    // the position of each character of the text in the collapsed text
+    let mut positions = Vec::new();
+    for c in text.chars() {
+        if c.is_whitespace() {
+            has_space = length > 0;
+        } else {
+            if has_space {
+                collapsed.push(' ');
+                length += 1;
+                has_space = false;
+            }
+            collapsed.push(c);
+            length += 1;
+        }
+        positions.push(if c.is_whitespace() { length } else { length - 1 });
+    }
+    positions.push(length);
+
+    let chars: Vec<char> = collapsed.chars().collect();
+    for range in emphasis.iter_mut() {
+        range.start = positions[cmp::min(range.start, positions.len() - 1)];
+        range.end = positions[cmp::min(range.end, positions.len() - 1)];
+
+        // emphasized whitespace at the bounds is not emphasis
+        while range.start < range.end && chars[range.start] == ' ' {
+            range.start += 1;
+        }
+        while range.start < range.end && chars[range.end - 1] == ' ' {
+            range.end -= 1;
+        }
+    }
+
+    collapsed
+}
+
+/// Normalizes the `Emphasis` of a line and joins the ranges of a style which
+/// are only separated by whitespace, like two emphasized words.
+fn join_emphasis(emphasis: &mut Vec<Emphasis>, text: &str) {
+    let chars: Vec<char> = text.chars().collect();
+    for range in emphasis.iter_mut() {
+        range.start = cmp::min(range.start, chars.len());
+        range.end = cmp::min(range.end, chars.len());
+    }
+    Emphasis::normalize(emphasis);
+
+    let mut joined: Vec<Emphasis> = Vec::new();
+    for range in emphasis.drain(..) {
+        let gap = joined.iter().rposition(|other| {
+            other.style == range.style && other.end <= range.start &&
+            chars[other.end..range.start].iter().all(|c| c.is_whitespace())
+        });
+        match gap {
+            Some(index) => joined[index].end = range.end,
+            None => joined.push(range),
+        }
+    }
+
+    Emphasis::normalize(&mut joined);
+    *emphasis = joined;
+}
+
+/// Checks if the whole line is bold and uppercase, like many scene headings.
+fn is_bold_heading(line: &str, emphasis: &[Emphasis]) -> bool {
+    let length = line.chars().count();
+    let is_bold = emphasis.iter().any(|range| {
+        range.style == EmphasisStyle::Bold && range.start == 0 && range.end >= length
+    });
+
+    is_bold && line.chars().any(|c| c.is_alphabetic()) && line.to_uppercase() == line
+}
+
+/// Appends a line to a text, separated by a space, together with the
+/// `Emphasis` of the line.
+fn append_line(text: &mut String, emphasis: &mut Vec<Emphasis>, line: &str, line_emphasis: &[Emphasis]) {
+    if text.len() > 0 {
+        text.push(' ');
+    }
+
+    let offset = text.chars().count();
+    let length = line.chars().count();
+    text.push_str(line);
+
+    for range in line_emphasis.iter().filter(|range| range.start < length) {
+        emphasis.push(Emphasis {
+            style: range.style,
+            start: offset + range.start,
+            end: offset + cmp::min(range.end, length),
+        });
+    }
+    join_emphasis(emphasis, text);
+}
+
+/// Splits the first page off the lines, if it is a title page.
+///
+/// Returns the `ScriptMetadata` of the title page and the remaining lines.
+fn extract_title_page(lines: Vec<(LineAttributes, String)>, rules: &ParseRules)
+    -> (ScriptMetadata, Vec<(LineAttributes, String)>) {
+    let first_page = match lines.first() {
+        Some(&(ref attributes, _)) => attributes.page,
+        None => return (Default::default(), lines),
+    };
+
+    let is_first_page = |&&(ref attributes, _): &&(LineAttributes, String)| attributes.page == first_page;
+    let is_script_left = lines.iter().any(|line| !is_first_page(&line));
+    let first_lines = lines.iter().filter(&is_first_page).map(|&(_, ref line)| line.as_ref());
+
+    if !is_script_left || !title_page::is_title_page(first_lines, rules) {
+        return (Default::default(), lines);
+    }
+
+    let (mut title_lines, lines): (Vec<_>, Vec<_>) = lines.into_iter().partition(|line| is_first_page(&line));
+    title_lines.sort_by(|a, b| (a.0.top, a.0.left).cmp(&(b.0.top, b.0.left)));
+
+    // lines further apart than one and a half line heights start a new block
+    let mut blocks: Vec<Vec<String>> = Vec::new();
+    let mut last_top = None;
+    for (attributes, line) in title_lines {
+        let is_new_block = match last_top {
+            Some(last_top) => (attributes.top - last_top) * 2 > cmp::max(attributes.height, 1) * 3,
+            None => true,
+        };
+        last_top = Some(attributes.top);
+
+        if is_new_block {
+            blocks.push(Vec::new());
+        }
+        blocks.last_mut().unwrap().push(line);
+    }
+
+    (title_page::extract_metadata(&blocks), lines)
+}
+
+/// Heuristically determines the `LayoutProfile` of the script.
+fn analyze_script(lines: &Vec<(LineAttributes, String)>, diagnostics: &mut Diagnostics)
+    -> Result<LayoutProfile, ParseError> {
+    let mut layout: LayoutProfile = Default::default();
+
+    // these maps are used to heuristically determine the LayoutProfile
+    let mut position_uses: HashMap<i32, u32> = HashMap::new();
+    let mut line_height_uses: HashMap<i32, u32> = HashMap::new();
+    let mut transition_position_uses: HashMap<i32, u32> = HashMap::new();
+
+    let mut last_page_number = None;
+    let mut last_line_height = 0;
+
+    for &(ref attributes, ref line) in lines.iter() {
+        if last_page_number != Some(attributes.page) {
+            last_page_number = Some(attributes.page);
+            last_line_height = 0;
+        }
+
+        // increase the count for the current left margin
+        *position_uses.entry(attributes.left).or_insert(0) += 1;
+
+        // increase the count for the current top position diff
+        *line_height_uses.entry(attributes.top - last_line_height).or_insert(0) += 1;
+        last_line_height = attributes.top;
+
+        if TransitionKind::from_text(line).is_some() {
+            *transition_position_uses.entry(attributes.left).or_insert(0) += 1;
+        }
+    }
+
+    // group positions which drift slightly between pages into columns
+    let mut columns = cluster_positions(&position_uses);
+    diagnostics.columns = columns.iter().map(|&(column, uses)| DetectedColumn {
+        column: column,
+        uses: uses,
+    }).collect();
+
+    // there should at least be 3 different columns
+    if columns.len() < 3 {
+        let mut position_uses_vec = position_uses.into_iter().collect::<Vec<_>>();
+        position_uses_vec.sort_by(|&a, &b| b.1.cmp(&a.1));
+        return Err(ParseError::UnknownLayout { position_uses: position_uses_vec });
+    }
+
+    // sort the top 3 columns by position again (right-exclusive range)
+    columns[0..3].sort_by(|a, b| b.0.position.cmp(&a.0.position));
+
+    layout.speaker = columns[0].0;
+    layout.dialog = columns[1].0;
+    layout.direction = columns[2].0;
+
+    // take the next column between speaker and dialog as speaker_direction
+    for &(column, _) in columns.iter().skip(3) {
+        if column.position > layout.dialog.position &&
+           column.position < layout.speaker.position {
+            layout.speaker_direction = Some(column);
+            break;
+        }
+    }
+
+    // use the most common column of transitions outside the other columns
+    for &(column, _) in cluster_positions(&transition_position_uses).iter() {
+        if layout.column_at(column.position).is_none() {
+            layout.transition = Some(column);
+            break;
+        }
+    }
+
+    // use to most used line diff value to determine the specific sections
+    let mut last_uses: u32 = 0;
+    for (&line_height, &uses) in line_height_uses.iter() {
+        if uses > last_uses {
+            layout.line_height = line_height;
+            last_uses = uses;
+        }
+    }
+
+    Ok(layout)
+}
+
+/// Groups `left` positions which are at most this far apart into a column.
+const MAX_COLUMN_DRIFT: i32 = 2;
+
+/// The maximum distance between the leftmost and rightmost position of a
+/// column.
+const MAX_COLUMN_WIDTH: i32 = 8;
+
+/// Clusters the given histogram of `left` positions into columns.
+///
+/// Neighbouring positions are put into the same column as long as the gap
+/// between them does not exceed `MAX_COLUMN_DRIFT` and the column does not
+/// get wider than `MAX_COLUMN_WIDTH`. Otherwise dense positions of centered
+/// text, like titles and transitions, would chain into one wide column
+/// swallowing its neighbours. Each column is placed at its most common
+/// position with a tolerance covering all its positions.
+///
+/// Returns the columns with their number of uses, sorted by uses descending.
+fn cluster_positions(position_uses: &HashMap<i32, u32>) -> Vec<(Column, u32)> {
+    let mut positions = position_uses.iter().map(|(&p, &u)| (p, u)).collect::<Vec<_>>();
+    positions.sort();
+
+    // the positions of each cluster, sorted by position
+    let mut clusters: Vec<Vec<(i32, u32)>> = Vec::new();
+    for (position, uses) in positions {
+        let is_drift = match clusters.last() {
+            Some(cluster) => {
+                position - cluster[cluster.len() - 1].0 <= MAX_COLUMN_DRIFT &&
+                position - cluster[0].0 <= MAX_COLUMN_WIDTH
+            }
+            None => false,
+        };
+
+        if is_drift {
+            clusters.last_mut().unwrap().push((position, uses));
+        } else {
+            clusters.push(vec![(position, uses)]);
+        }
+    }
+
+    let mut columns = clusters.into_iter().map(|cluster| {
+        // prefer the leftmost position if several are equally common
+        let mut center = cluster[0];
+        for &(position, uses) in cluster.iter() {
+            if uses > center.1 {
+                center = (position, uses);
+            }
+        }
+
+        let first = cluster[0].0;
+        let last = cluster[cluster.len() - 1].0;
+        let column = Column {
+            position: center.0,
+            tolerance: cmp::max(center.0 - first, last - center.0),
+        };
+
+        (column, cluster.iter().map(|&(_, uses)| uses).sum())
+    }).collect::<Vec<(Column, u32)>>();
+
+    // the sort is stable, so equally common columns stay sorted by position
+    columns.sort_by(|a, b| b.1.cmp(&a.1));
+    columns
+}
+
+#[derive(Debug, Clone)]
+enum ScriptPart {
+    Separator,
+    ScenePart(ScenePart),
+    /// The heading of the location and its scene number
+    LocationChange(String, Option<String>),
+}
+
+/// Lines at most this far apart vertically are considered to be on the same
+/// line of the page, like a heading and its scene numbers in the margins.
+const MAX_LINE_DRIFT: i32 = 3;
+
+/// The scene numbers and revision marks found in the margins of a script.
+#[derive(Debug, Clone, Default)]
+struct MarginMarks {
+    /// The scene numbers by the page and top position of their heading
+    scene_numbers: HashMap<(u32, i32), String>,
+    /// The page and top position of the lines marked as revised
+    revised_lines: HashSet<(u32, i32)>,
+    /// The indices of the lines containing the marks
+    mark_lines: HashSet<usize>,
+}
+
+lazy_static! {
+    static ref SCENE_NUMBER: Regex = Regex::new(r"^\d+[A-Z]{0,2}\.?$").unwrap();
+}
+
+fn is_scene_number(line: &str) -> bool {
+    SCENE_NUMBER.is_match(line)
+}
+
+fn is_revision_mark(line: &str) -> bool {
+    line.len() > 0 && line.chars().all(|c| c == '*')
+}
+
+/// Splits a trailing revision mark like in `She runs. *` off the line.
+fn split_revision_mark(line: &str) -> (&str, bool) {
+    let text = line.trim_end_matches('*');
+    if text.len() < line.len() && text.ends_with(char::is_whitespace) {
+        (text.trim_end(), true)
+    } else {
+        (line, false)
+    }
+}
+
+/// Finds the scene numbers and revision marks in the margins.
+///
+/// Marks are lines outside of all columns which consist only of a scene
+/// number or asterisks. They belong to the nearest line of the same page at
+/// the same height, which has to be a heading for scene numbers.
+fn find_margin_marks(layout: &LayoutProfile, rules: &ParseRules, lines: &Vec<(LineAttributes, String)>)
+    -> MarginMarks {
+    let mut marks: MarginMarks = Default::default();
+
+    let is_mark = |line: &str| is_scene_number(line) || is_revision_mark(line);
+
+    for (index, &(ref attributes, ref line)) in lines.iter().enumerate() {
+        if layout.column_at(attributes.left).is_some() || !is_mark(line) {
+            continue;
+        }
+
+        let neighbour = lines.iter().filter(|&&(ref other, ref other_line)| {
+            other.page == attributes.page &&
+            (other.top - attributes.top).abs() <= MAX_LINE_DRIFT &&
+            other_line.len() > 0 && !is_mark(other_line)
+        }).min_by_key(|&&(ref other, _)| (other.top - attributes.top).abs());
+
+        if let Some(&(ref other, ref other_line)) = neighbour {
+            if is_revision_mark(line) {
+                marks.revised_lines.insert((other.page, other.top));
+                marks.mark_lines.insert(index);
+            } else if rules.headings.is_heading(other_line) {
+                let number = line.trim_end_matches('.').to_string();
+                marks.scene_numbers.insert((other.page, other.top), number);
+                marks.mark_lines.insert(index);
+            }
+        }
+    }
+
+    marks
+}
+
+/// The minimum number of pages a running header has to appear on.
+const MIN_RUNNING_HEADER_PAGES: usize = 3;
+
+lazy_static! {
+    static ref PAGE_NUMBER: Regex = Regex::new(r"(?i)^(?:page\s+)?[-–]?\s*\d+\s*[-–]?\.?$").unwrap();
+    static ref MORE: Regex = Regex::new(r"(?i)^\(\s*more\s*\)$").unwrap();
+    static ref CONTINUED: Regex = Regex::new(
+        r"(?i)^(?:\d+[A-Z]{0,2}\.?\s+)?\(?\s*continued\s*\)?:?(?:\s*\(\d+\))?(?:\s+\d+[A-Z]{0,2}\.?)?$"
+    ).unwrap();
+}
+
+fn is_page_number(line: &str) -> bool {
+    PAGE_NUMBER.is_match(line)
+}
+
+/// Checks for the `(MORE)` below a dialog which continues on the next page.
+fn is_more(line: &str) -> bool {
+    MORE.is_match(line)
+}
+
+/// Checks for `(CONTINUED)` and `CONTINUED:` at page breaks, which can be
+/// surrounded by scene numbers.
+fn is_continued(line: &str) -> bool {
+    CONTINUED.is_match(line)
+}
+
+/// Reduces a line to its letters, so that running headers can be compared
+/// regardless of their page numbers and dates.
+fn normalize_running_header(line: &str) -> String {
+    line.chars().filter(|c| c.is_alphabetic()).flat_map(|c| c.to_uppercase()).collect()
+}
+
+/// Removes the artifacts of the page breaks from the lines.
+///
+/// These are page numbers and running headers at the top or bottom of the
+/// pages, `(MORE)` and `(CONTINUED)` as well as the repeated cue of a
+/// speaker whose dialog continues on the next page, like `WOODY (CONT'D)`.
+///
+/// Returns the remaining lines and the indices of the lines which continue
+/// a dialog or direction of the previous page. A direction continues if the
+/// previous page ends with a direction and the next one starts with one.
+fn remove_artifacts(layout: &LayoutProfile, rules: &ParseRules,
+                    lines: Vec<(LineAttributes, String)>, diagnostics: &mut Diagnostics)
+    -> (Vec<(LineAttributes, String)>, HashSet<usize>) {
+    // the top positions of the first and last line of each page
+    let mut page_bounds: HashMap<u32, (i32, i32)> = HashMap::new();
+    for &(ref attributes, _) in lines.iter().filter(|&&(_, ref line)| line.len() > 0) {
+        let bounds = page_bounds.entry(attributes.page).or_insert((attributes.top, attributes.top));
+        bounds.0 = cmp::min(bounds.0, attributes.top);
+        bounds.1 = cmp::max(bounds.1, attributes.top);
+    }
+
+    // lines at the top or bottom of a page, which are not next to a heading
+    let edge_lines: HashSet<usize> = lines.iter().enumerate().filter(|&(_, &(ref attributes, ref line))| {
+        let (top, bottom) = page_bounds.get(&attributes.page).cloned().unwrap_or((0, 0));
+        let is_edge = attributes.top - top <= MAX_LINE_DRIFT || bottom - attributes.top <= MAX_LINE_DRIFT;
+
+        is_edge && line.len() > 0 && !lines.iter().any(|&(ref other, ref other_line)| {
+            other.page == attributes.page && (other.top - attributes.top).abs() <= MAX_LINE_DRIFT &&
+            rules.headings.is_heading(other_line)
+        })
+    }).map(|(index, _)| index).collect();
+
+    // the pages on which each text appears at the top or bottom
+    let mut running_header_pages: HashMap<String, HashSet<u32>> = HashMap::new();
+    for &index in edge_lines.iter() {
+        let (ref attributes, ref line) = lines[index];
+        let text = normalize_running_header(line);
+        if text.len() > 0 && layout.column_at(attributes.left) != Some(ColumnKind::Speaker) {
+            running_header_pages.entry(text).or_insert(HashSet::new()).insert(attributes.page);
+        }
+    }
+    let is_running_header = |line: &str| {
+        running_header_pages.get(&normalize_running_header(line)).map_or(false, |pages| {
+            pages.len() >= MIN_RUNNING_HEADER_PAGES && pages.len() * 2 >= page_bounds.len()
+        })
+    };
+
+    let mut cleaned_lines = Vec::new();
+    let mut continued_lines = HashSet::new();
+
+    // states for merging dialogs across page breaks
+    let mut last_page = None;
+    let mut is_first_line_of_page = false;
+    let mut current_speaker: Option<String> = None;
+    let mut has_more = false;
+    let mut continues_part = false;
+    let mut ends_with_direction = false;
+
+    for (index, (attributes, line)) in lines.into_iter().enumerate() {
+        if line.len() == 0 {
+            cleaned_lines.push((attributes, line));
+            continue;
+        }
+
+        let is_more_line = is_more(&line);
+        let is_artifact = is_more_line || is_continued(&line) ||
+                          (edge_lines.contains(&index) && (is_page_number(&line) || is_running_header(&line)));
+        let column = layout.column_at(attributes.left);
+
+        let mut is_recue = false;
+        if !is_artifact {
+            if last_page != Some(attributes.page) {
+                last_page = Some(attributes.page);
+                is_first_line_of_page = true;
+            }
+
+            // a dialog at the end of the previous page continues, either with
+            // the repeated cue of its speaker or directly
+            if is_first_line_of_page && current_speaker.is_some() {
+                if column == Some(ColumnKind::Speaker) {
+                    let (name, mode) = extract_speaker(&line);
+                    is_recue = Some(&name) == current_speaker.as_ref() &&
+                               (has_more || mode.contains(&DialogMode::Continued));
+                    continues_part = is_recue;
+                } else if has_more {
+                    continues_part = column == Some(ColumnKind::Dialog) ||
+                                       column == Some(ColumnKind::SpeakerDirection);
+                }
+            } else if is_first_line_of_page && ends_with_direction {
+                continues_part = is_direction_line(column, rules, &line, &attributes.emphasis);
+            }
+            is_first_line_of_page = false;
+        }
+
+        if is_artifact || is_recue {
+            if is_more_line {
+                has_more = true;
+            }
+            diagnostics.artifacts.push(UnmatchedLine {
+                page: attributes.page,
+                top: attributes.top,
+                left: attributes.left,
+                text: line,
+            });
+            continue;
+        }
+
+        if continues_part {
+            continued_lines.insert(cleaned_lines.len());
+            continues_part = false;
+        }
+
+        ends_with_direction = is_direction_line(column, rules, &line, &attributes.emphasis);
+        match column {
+            Some(ColumnKind::Speaker) => {
+                current_speaker = Some(extract_speaker(&line).0);
+                has_more = false;
+            }
+            Some(ColumnKind::Direction) | Some(ColumnKind::Transition) => {
+                current_speaker = None;
+                has_more = false;
+            }
+            _ => {}
+        }
+
+        cleaned_lines.push((attributes, line));
+    }
+
+    (cleaned_lines, continued_lines)
+}
+
+/// Checks if the line is part of a direction, and not a heading or
+/// transition in the direction column.
+fn is_direction_line(column: Option<ColumnKind>, rules: &ParseRules, line: &str, emphasis: &[Emphasis]) -> bool {
+    column == Some(ColumnKind::Direction) && !rules.headings.is_heading(line) &&
+    TransitionKind::from_text(line).is_none() && !(rules.bold_headings && is_bold_heading(line, emphasis))
+}
+
+/// The indices of the lines of the left and right column of a dual dialog.
+#[derive(Debug, Clone, Default)]
+struct DualDialogLines {
+    left: Vec<usize>,
+    right: Vec<usize>,
+}
+
+/// Checks if the line could be the character cue of a dual dialog.
+fn is_dual_dialog_cue(line: &str, rules: &ParseRules) -> bool {
+    let (name, _) = extract_speaker(line);
+    name.chars().any(|c| c.is_alphabetic()) && name.to_uppercase() == name &&
+    TransitionKind::from_text(line).is_none() && !rules.headings.is_heading(line)
+}
+
+/// Finds the dual dialogs, which start with two character cues side by side.
+///
+/// The lines following the cues are split into the left and right column at
+/// the middle between both cues, until the next section gap or page break.
+/// Returns the lines of each dual dialog by the index of its left cue.
+fn find_dual_dialogs(layout: &LayoutProfile, rules: &ParseRules, lines: &Vec<(LineAttributes, String)>,
+                     margin_marks: &MarginMarks)
+    -> HashMap<usize, DualDialogLines> {
+    let mut dual_dialogs = HashMap::new();
+
+    let content: Vec<usize> = (0..lines.len()).filter(|index| {
+        lines[*index].1.len() > 0 && !margin_marks.mark_lines.contains(index)
+    }).collect();
+
+    let mut position = 0;
+    while position + 1 < content.len() {
+        let (ref left_cue, ref left_line) = lines[content[position]];
+        let (ref right_cue, ref right_line) = lines[content[position + 1]];
+
+        // the left cue is printed right of the directions, not as a heading
+        let is_dual_dialog = left_cue.page == right_cue.page &&
+                             (left_cue.top - right_cue.top).abs() <= MAX_LINE_DRIFT &&
+                             left_cue.left < right_cue.left &&
+                             left_cue.left > layout.direction.position + layout.direction.tolerance &&
+                             is_dual_dialog_cue(split_revision_mark(left_line).0, rules) &&
+                             is_dual_dialog_cue(split_revision_mark(right_line).0, rules);
+        if !is_dual_dialog {
+            position += 1;
+            continue;
+        }
+
+        let middle = (left_cue.left + right_cue.left) / 2;
+        let mut columns = DualDialogLines {
+            left: vec![content[position]],
+            right: vec![content[position + 1]],
+        };
+        let mut bottom = cmp::max(left_cue.top, right_cue.top);
+
+        position += 2;
+        while position < content.len() {
+            let attributes = &lines[content[position]].0;
+            if attributes.page != left_cue.page || attributes.top < left_cue.top ||
+               attributes.top - bottom > layout.section_gap {
+                break;
+            }
+
+            if attributes.left < middle {
+                columns.left.push(content[position]);
+            } else {
+                columns.right.push(content[position]);
+            }
+            bottom = cmp::max(bottom, attributes.top);
+            position += 1;
+        }
+
+        dual_dialogs.insert(columns.left[0], columns);
+    }
+
+    dual_dialogs
+}
+
+/// Extracts a `Dialog` from the lines of one column of a dual dialog.
+///
+/// The columns are too narrow to tell parentheticals from dialog by their
+/// position, so parentheticals are recognized by their brackets.
+fn extract_dual_dialog_column(lines: &Vec<(LineAttributes, String)>, indices: &[usize],
+                              margin_marks: &MarginMarks)
+    -> Dialog {
+    let (ref cue_attributes, ref cue) = lines[indices[0]];
+    let (cue, is_revised) = split_revision_mark(cue);
+    let (speaker, mode) = extract_speaker(cue);
+
+    let mut dialog: Vec<DialogPart> = Vec::new();
+    let mut end_attributes = cue_attributes;
+    let mut revised = is_revised;
+    let mut source = Vec::new();
+
+    for &index in indices.iter() {
+        let (ref attributes, ref line) = lines[index];
+        let (line, is_revised) = split_revision_mark(line);
+        revised |= is_revised || margin_marks.revised_lines.contains(&(attributes.page, attributes.top));
+        SourceSpan::extend(&mut source, attributes.source_span());
+        if attributes.top > end_attributes.top {
+            end_attributes = attributes;
+        }
+
+        if index == indices[0] {
+            continue;
+        }
+
+        // parentheticals can span several lines until the closing bracket
+        let in_parenthetical = match dialog.last() {
+            Some(&DialogPart::Direction(ref direction, _, _)) => !direction.ends_with(')'),
+            _ => false,
+        };
+
+        if line.starts_with('(') || in_parenthetical {
+            if !in_parenthetical {
+                dialog.push(DialogPart::Direction(String::new(), Vec::new(), Vec::new()));
+            }
+
+            if let Some(&mut DialogPart::Direction(ref mut direction, ref mut source, ref mut emphasis)) = dialog.last_mut() {
+                append_line(direction, emphasis, line, &attributes.emphasis);
+                SourceSpan::extend(source, attributes.source_span());
+            }
+        } else {
+            if let Some(&DialogPart::Dialog(_, _, _)) = dialog.last() {
+            } else {
+                dialog.push(DialogPart::Dialog(String::new(), Vec::new(), Vec::new()));
+            }
+
+            if let Some(&mut DialogPart::Dialog(ref mut text, ref mut source, ref mut emphasis)) = dialog.last_mut() {
+                append_line(text, emphasis, line, &attributes.emphasis);
+                SourceSpan::extend(source, attributes.source_span());
+            }
+        }
+    }
+
+    Dialog {
+        speaker: speaker,
+        mode: mode,
+        dialog: dialog,
+        page: cue_attributes.page,
+        end_page: end_attributes.page,
+        top: Some(cue_attributes.top),
+        end_top: Some(end_attributes.top),
+        revised: revised,
+        source: source,
+    }
+}
+
+fn extract_script_parts(layout: &LayoutProfile, rules: &ParseRules, lines: &Vec<(LineAttributes, String)>,
+                        continued_lines: &HashSet<usize>, diagnostics: &mut Diagnostics)
+    -> Vec<ScriptPart> {
+    let mut script_parts: Vec<ScriptPart> = Vec::new();
+    let mut last_top_position = 0;
+
+    let margin_marks = find_margin_marks(layout, rules, lines);
+    let dual_dialogs = find_dual_dialogs(layout, rules, lines, &margin_marks);
+    let dual_dialog_lines: HashSet<usize> = dual_dialogs.values()
+                                                        .flat_map(|columns| columns.left.iter().chain(columns.right.iter()))
+                                                        .cloned()
+                                                        .collect();
+
+    for (index, line) in lines.iter().enumerate() {
+        let &(ref attributes, ref line) = line;
+
+        if line.len() == 0 {
+            continue;
+        }
+
+        let column = layout.column_at(attributes.left);
+
+        diagnostics.lines += 1;
+        diagnostics.characters += line.chars().count() as u32;
+
+        if margin_marks.mark_lines.contains(&index) {
+            continue;
+        }
+
+        // the dual dialogs are extracted as a whole at their first cue
+        if dual_dialog_lines.contains(&index) && !dual_dialogs.contains_key(&index) {
+            continue;
+        }
+
+        let (line, is_revised) = split_revision_mark(line);
+        let is_revised = is_revised ||
+                         margin_marks.revised_lines.contains(&(attributes.page, attributes.top));
+        let heading = rules.headings.parse_heading(line).map(|(heading, number)| {
+            let margin_number = margin_marks.scene_numbers.get(&(attributes.page, attributes.top));
+            (heading, number.or(margin_number.cloned()))
+        });
+
+        // check if a new section starts, unless the line continues a dialog
+        // or direction after a page break
+        let is_gap = attributes.top - last_top_position > layout.section_gap ||
+                     attributes.top - last_top_position < 0;
+        if is_gap && !continued_lines.contains(&index) {
+            // used to separate two consecutive script parts of the
+            // same type. this is just a implementation detail of
+            // the parsing. the Separator can be ignored later.
+            script_parts.push(ScriptPart::Separator);
+
+            diagnostics.section_breaks.push(SectionBreak {
+                page: attributes.page,
+                top: attributes.top,
+                gap: attributes.top - last_top_position,
+            });
+        }
+
+        if let Some(columns) = dual_dialogs.get(&index) {
+            let left = extract_dual_dialog_column(lines, &columns.left, &margin_marks);
+            let right = extract_dual_dialog_column(lines, &columns.right, &margin_marks);
+            script_parts.push(ScriptPart::ScenePart(ScenePart::DualDialog {
+                left: left,
+                right: right,
+            }));
+
+            last_top_position = columns.left.iter().chain(columns.right.iter())
+                                       .map(|&index| lines[index].0.top)
+                                       .max()
+                                       .unwrap_or(attributes.top);
+            continue;
+        }
+
+        let transition = TransitionKind::from_text(line);
+
+        // a bold line standing alone in its section can be a heading without
+        // a prefix
+        let heading = heading.or_else(|| {
+            let next_line = lines[index + 1..].iter().enumerate().find(|&(offset, &(_, ref next))| {
+                next.len() > 0 && !margin_marks.mark_lines.contains(&(index + 1 + offset))
+            });
+            let ends_section = next_line.map_or(true, |(_, &(ref next, _))| {
+                next.top - attributes.top > layout.section_gap || next.top < attributes.top
+            });
+
+            if rules.bold_headings && column == Some(ColumnKind::Direction) && transition.is_none() &&
+               is_gap && ends_section && is_bold_heading(line, &attributes.emphasis) {
+                let margin_number = margin_marks.scene_numbers.get(&(attributes.page, attributes.top));
+                Some((line.to_string(), margin_number.cloned()))
+            } else {
+                None
+            }
+        });
+
+        if column == Some(ColumnKind::Transition) || (column.is_none() && transition.is_some()) {
+            script_parts.push(ScriptPart::ScenePart(ScenePart::Transition {
+                kind: transition.unwrap_or(TransitionKind::Other),
+                text: line.to_string(),
+                page: attributes.page,
+                end_page: attributes.page,
+                top: Some(attributes.top),
+                end_top: Some(attributes.top),
+                source: vec![attributes.source_span()],
+            }));
+        } else if column == Some(ColumnKind::Direction) || (column.is_none() && heading.is_some()) {
+            if let Some((heading, number)) = heading {
+                script_parts.push(ScriptPart::LocationChange(heading, number));
+            } else if let Some(kind) = transition {
+                script_parts.push(ScriptPart::ScenePart(ScenePart::Transition {
+                    kind: kind,
+                    text: line.to_string(),
+                    page: attributes.page,
+                    end_page: attributes.page,
+                    top: Some(attributes.top),
+                    end_top: Some(attributes.top),
+                    source: vec![attributes.source_span()],
+                }));
+            } else {
+                // ensure the last script part is a direction
+                if let Some(&ScriptPart::ScenePart(ScenePart::Direction{..})) = script_parts.last() {
+                } else {
+                    script_parts.push(ScriptPart::ScenePart(
+                        ScenePart::Direction{
+                            direction: String::new(),
+                            page: attributes.page,
+                            end_page: attributes.page,
+                            top: Some(attributes.top),
+                            end_top: Some(attributes.top),
+                            revised: false,
+                            source: Vec::new(),
+                            emphasis: Vec::new(),
+                        }));
+                }
+
+                if let Some(&mut ScriptPart::ScenePart(ScenePart::Direction{ref mut direction, ref mut end_page, ref mut end_top, ref mut revised, ref mut source, ref mut emphasis, ..})) = script_parts.last_mut() {
+                    *revised |= is_revised;
+                    *end_page = attributes.page;
+                    *end_top = Some(attributes.top);
+                    SourceSpan::extend(source, attributes.source_span());
+                    append_line(direction, emphasis, line, &attributes.emphasis);
+                }
+            }
+        } else if column == Some(ColumnKind::Speaker) ||
+                  column == Some(ColumnKind::SpeakerDirection) ||
+                  column == Some(ColumnKind::Dialog) {
+            // Ensure the last script part is a dialog
+            if let Some(&ScriptPart::ScenePart(ScenePart::Dialog(_))) = script_parts.last() {
+            } else {
+                script_parts.push(ScriptPart::ScenePart(
+                    ScenePart::Dialog(Dialog {
+                        speaker: String::new(),
+                        mode: Vec::new(),
+                        dialog: Vec::new(),
+                        page: attributes.page,
+                        end_page: attributes.page,
+                        top: Some(attributes.top),
+                        end_top: Some(attributes.top),
+                        revised: false,
+                        source: Vec::new(),
+                    })));
+            }
+
+            // get the dialog, should never fail (see above)
+            if let Some(&mut ScriptPart::ScenePart(ScenePart::Dialog(Dialog {ref mut speaker, ref mut mode, ref mut dialog, ref mut end_page, ref mut end_top, ref mut revised, ref mut source, ..}))) = script_parts.last_mut() {
+                *revised |= is_revised;
+                *end_page = attributes.page;
+                *end_top = Some(attributes.top);
+                SourceSpan::extend(source, attributes.source_span());
+                if column == Some(ColumnKind::Speaker) {
+                    // there is only one speaker per dialog
+                    let (name, extensions) = extract_speaker(line);
+                    speaker.push_str(&name);
+                    mode.extend(extensions);
+                } else if column == Some(ColumnKind::SpeakerDirection) {
+                    if let Some(&DialogPart::Direction(_, _, _)) = dialog.last() {
+                    } else {
+                        dialog.push(DialogPart::Direction(String::new(), Vec::new(), Vec::new()));
+                    }
+
+                    if let Some(&mut DialogPart::Direction(ref mut direction, ref mut source, ref mut emphasis)) = dialog.last_mut() {
+                        append_line(direction, emphasis, line, &attributes.emphasis);
+                        SourceSpan::extend(source, attributes.source_span());
+                    }
+                } else if column == Some(ColumnKind::Dialog) {
+                    if let Some(&DialogPart::Dialog(_, _, _)) = dialog.last() {
+                    } else {
+                        dialog.push(DialogPart::Dialog(String::new(), Vec::new(), Vec::new()));
+                    }
+
+                    if let Some(&mut DialogPart::Dialog(ref mut dialog, ref mut source, ref mut emphasis)) = dialog.last_mut() {
+                        append_line(dialog, emphasis, line, &attributes.emphasis);
+                        SourceSpan::extend(source, attributes.source_span());
+                    }
+                }
+            }
+        } else {
+            diagnostics.skipped_characters += line.chars().count() as u32;
+            diagnostics.skipped_lines.push(UnmatchedLine {
+                page: attributes.page,
+                top: attributes.top,
+                left: attributes.left,
+                text: line.to_string(),
+            });
+        }
+
+        last_top_position = attributes.top;
+    }
+
+    script_parts
+}
+
+lazy_static! {
+    static ref SPEAKER_SEPARATOR: Regex = Regex::new(r"(?i)\s*(?:&|\+|/|,|\bAND\b)\s*").unwrap();
+    static ref SPEAKER_EXTENSION: Regex = Regex::new(r"\(([^()]*)\)").unwrap();
+}
+
+/// The cues naming a group of characters instead of a character.
+const GROUP_NAMES: &'static [&'static str] = &["ALL", "BOTH", "CHORUS", "CROWD", "EVERYBODY", "EVERYONE", "GROUP",
+                                               "OTHERS", "VOICES"];
+
+fn is_group_name(name: &str) -> bool {
+    let name = name.to_uppercase();
+    let name = name.trim_start_matches("THE ");
+    GROUP_NAMES.contains(&name)
+}
+
+/// Splits the extensions like `(V.O.)` off the given speaker cue.
+fn extract_speaker(cue: &str) -> (String, Vec<DialogMode>) {
+    let mut extensions = Vec::new();
+    for captures in SPEAKER_EXTENSION.captures_iter(cue) {
+        extensions.push(DialogMode::from_extension(captures.at(1).unwrap()));
+    }
+
+    let name = SPEAKER_EXTENSION.replace_all(cue, "");
+    (name.split_whitespace().collect::<Vec<_>>().join(" "), extensions)
+}
+
+fn extract_scenes(script_parts: &Vec<ScriptPart>, rules: &ParseRules) -> Vec<Scene> {
+    let mut scenes = Vec::new();
+
+    // scene with default (empty) location
+    let default_scene: Scene = vec![Default::default()];
+    scenes.push(default_scene.clone());
+
+    for script_part in script_parts.iter() {
+        use self::ScriptPart::*;
+        match script_part {
+            &LocationChange(ref heading, ref number) => {
+                // unwraps are safe, see default_scene
+                let mut current_scene = scenes.last_mut().unwrap();
+
+                if current_scene.last().unwrap().parts.len() == 0 {
+                    current_scene.pop();
+                }
+
+                let mut location = rules.headings.extract_location(heading);
+                location.number = number.clone();
+                current_scene.push(location);
+            }
+            &ScenePart(ref scene_part) => {
+                {
+                    let scene_parts = &mut scenes.last_mut().unwrap().last_mut().unwrap().parts;
+                    scene_parts.push(scene_part.clone());
+                }
+
+                // the transition ends the current scene
+                if let &::ScenePart::Transition { kind, .. } = scene_part {
+                    if rules.scene_transitions.contains(&kind) {
+                        scenes.push(default_scene.clone());
+                    }
+                }
+            }
+            &Separator => {} //ignore
+        }
+    }
+
+    scenes
+}
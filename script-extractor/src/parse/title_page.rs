@@ -0,0 +1,191 @@
+//! Extracting the `ScriptMetadata` from the title page of a script.
+//!
+//! Title pages have no fixed structure, so their lines are classified
+//! heuristically: the first lines are the title, the lines after each credit
+//! like `Written by` are its authors, and the remaining lines are the
+//! source, the draft date, the revision or the contact block.
+
+/// Extracts the `ScriptMetadata` from the lines of a title page.
+///
+/// The lines are grouped into blocks, which are separated by larger vertical
+/// gaps. A title or contact block only continues within its block, while
+/// the authors can also follow in the next block after a credit or a line
+/// like `and`.
+///
+/// # Examples
+///
+/// ```
+/// # use script_extractor::Credit;
+/// # use script_extractor::parse::title_page::extract_metadata;
+/// let blocks = vec![vec!["TOY STORY 3".to_string()],
+///                   vec!["Written by".to_string()],
+///                   vec!["Michael Arndt".to_string()],
+///                   vec!["Story by".to_string(),
+///                        "John Lasseter & Andrew Stanton".to_string()],
+///                   vec!["Pixar Animation Studios".to_string(),
+///                        "1200 Park Avenue".to_string()],
+///                   vec!["Blue Revision".to_string(), "March 3, 2009".to_string()]];
+///
+/// let metadata = extract_metadata(&blocks);
+/// assert_eq!(metadata.title, Some("TOY STORY 3".to_string()));
+/// assert_eq!(metadata.credits, vec![
+///     Credit { credit: "Written by".to_string(), authors: vec!["Michael Arndt".to_string()] },
+///     Credit {
+///         credit: "Story by".to_string(),
+///         authors: vec!["John Lasseter".to_string(), "Andrew Stanton".to_string()],
+///     },
+/// ]);
+/// assert_eq!(metadata.contact, Some("Pixar Animation Studios\n1200 Park Avenue".to_string()));
+/// assert_eq!(metadata.revision, Some("Blue Revision".to_string()));
+/// assert_eq!(metadata.draft_date, Some("March 3, 2009".to_string()));
+/// ```
+pub fn extract_metadata(blocks: &[Vec<String>]) -> ScriptMetadata {
+    let mut metadata: ScriptMetadata = Default::default();
+
+    let mut awaits_authors = false;
+    for block in blocks.iter() {
+        let mut field = if awaits_authors { Field::Authors } else { Field::None };
+
+        for line in block.iter() {
+            let line = line.trim();
+            if line.len() == 0 {
+                continue;
+            }
+
+            if let Some((credit, authors)) = split_credit(line) {
+                metadata.credits.push(Credit {
+                    credit: credit,
+                    authors: split_authors(&authors),
+                });
+                awaits_authors = authors.len() == 0;
+                field = Field::Authors;
+            } else if field == Field::Authors && is_author_separator(line) {
+                awaits_authors = true;
+            } else if line.to_lowercase().starts_with("based on") {
+                metadata.source = Some(line.to_string());
+                field = Field::Source;
+            } else if is_revision(line) {
+                metadata.revision = Some(line.to_string());
+                field = Field::None;
+            } else if is_draft_date(line) {
+                metadata.draft_date = Some(line.to_string());
+                field = Field::None;
+            } else {
+                if field == Field::None {
+                    field = if metadata.title.is_none() && metadata.credits.is_empty() {
+                        Field::Title
+                    } else {
+                        Field::Contact
+                    };
+                }
+
+                match field {
+                    Field::Title => append_line(&mut metadata.title, line, " "),
+                    Field::Authors => {
+                        // authors always follow a credit
+                        if let Some(credit) = metadata.credits.last_mut() {
+                            credit.authors.extend(split_authors(line));
+                        }
+                        awaits_authors = false;
+                    }
+                    Field::Source => append_line(&mut metadata.source, line, " "),
+                    Field::Contact => append_line(&mut metadata.contact, line, "\n"),
+                    Field::None => {}
+                }
+            }
+        }
+    }
+
+    metadata
+}
+
+/// Checks if the given lines of the first page look like a title page.
+///
+/// A title page contains no scene headings and no transitions, but credits
+/// the authors or mentions the source, the draft or the revision.
+pub fn is_title_page<'a, I>(lines: I, rules: &ParseRules) -> bool where I: Iterator<Item = &'a str> {
+    let mut has_metadata = false;
+
+    for line in lines {
+        let line = line.trim();
+        if rules.headings.is_heading(line) || TransitionKind::from_text(line).is_some() {
+            return false;
+        }
+
+        has_metadata |= split_credit(line).is_some() || line.to_lowercase().starts_with("based on") ||
+                        is_revision(line) || is_draft_date(line);
+    }
+
+    has_metadata
+}
+
+
+use ::{Credit, ScriptMetadata, TransitionKind};
+use parse::ParseRules;
+use regex::Regex;
+
+lazy_static! {
+    static ref CREDIT: Regex = Regex::new(
+        r"(?i)^((?:(?:original\s+)?(?:screenplay|screen\s+play|teleplay|story|script|written)(?:\s+and\s+directed)?\s+)?by):?(?:\s+(.+))?$"
+    ).unwrap();
+    static ref AUTHOR_SEPARATOR: Regex = Regex::new(r"(?i)\s*(?:&|,|\band\b)\s*").unwrap();
+    static ref REVISION: Regex = Regex::new(r"(?i)\brevis(?:ed|ions?)\b").unwrap();
+    static ref DRAFT: Regex = Regex::new(r"(?i)\bdraft\b").unwrap();
+    static ref NUMERIC_DATE: Regex = Regex::new(r"\b\d{1,4}[/.-]\d{1,2}[/.-]\d{1,4}\b").unwrap();
+    static ref NAMED_DATE: Regex = Regex::new(
+        r"(?i)\b(?:jan(?:uary)?|feb(?:ruary)?|mar(?:ch)?|apr(?:il)?|may|june?|july?|aug(?:ust)?|sep(?:t|tember)?|oct(?:ober)?|nov(?:ember)?|dec(?:ember)?)\b\.?,?\s+\d"
+    ).unwrap();
+}
+
+/// The field of the metadata which is continued by the following lines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    None,
+    Title,
+    Authors,
+    Source,
+    Contact,
+}
+
+/// Splits a credit like `Screenplay by` or `Written by John Doe` into the
+/// credit and the authors following it on the same line.
+fn split_credit(line: &str) -> Option<(String, String)> {
+    CREDIT.captures(line).map(|captures| {
+        (captures.at(1).unwrap().to_string(), captures.at(2).unwrap_or("").to_string())
+    })
+}
+
+/// Splits a line naming several authors, like `John Lasseter & Andrew Stanton`.
+fn split_authors(line: &str) -> Vec<String> {
+    AUTHOR_SEPARATOR.split(line)
+                    .map(|author| author.trim())
+                    .filter(|author| author.len() > 0)
+                    .map(|author| author.to_string())
+                    .collect()
+}
+
+/// Checks if the line joins the authors of two blocks, like `and`.
+fn is_author_separator(line: &str) -> bool {
+    let line = line.to_lowercase();
+    line == "and" || line == "&"
+}
+
+fn is_revision(line: &str) -> bool {
+    REVISION.is_match(line)
+}
+
+/// Checks if the line contains a date or names a draft.
+fn is_draft_date(line: &str) -> bool {
+    DRAFT.is_match(line) || NUMERIC_DATE.is_match(line) || NAMED_DATE.is_match(line)
+}
+
+/// Appends a line to an optional field, separated by `separator`.
+fn append_line(field: &mut Option<String>, line: &str, separator: &str) {
+    match field {
+        &mut Some(ref mut text) => {
+            text.push_str(separator);
+            text.push_str(line);
+        }
+        &mut None => *field = Some(line.to_string()),
+    }
+}
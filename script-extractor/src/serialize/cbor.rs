@@ -0,0 +1,17 @@
+//! Serialize `Script`s to `CBOR`
+
+/// Serialize the given `Script` into `CBOR`
+///
+/// Serialize the given `Script` into the binary [CBOR] format with the same
+/// structure as the `json` written by `serialize::json::format_script`.
+///
+/// [CBOR]: https://cbor.io
+pub fn format_script<W: Write>(script: &Script, output: &mut W) -> io::Result<()> {
+    try!(serde_cbor::to_writer(&mut *output, script).map_err(|e| io::Error::new(io::ErrorKind::Other, e)));
+    output.flush()
+}
+
+
+use ::Script;
+use serde_cbor;
+use std::io::{self, Write};
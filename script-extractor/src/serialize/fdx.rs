@@ -0,0 +1,242 @@
+//! Serialize `Script`s to Final Draft (`.fdx`)
+
+/// Serialize the given `Script` into a Final Draft document
+///
+/// Serialize the given `Script` into the xml format used by Final Draft and
+/// write it to the given `Writer`. Only the transitions of the script are
+/// written and the first paragraph of each page starts a new page. The
+/// metadata is written as centered title page. Dual dialogs are wrapped into
+/// a `DualDialogue` element.
+///
+/// # Example
+///
+/// ```xml
+/// <?xml version="1.0" encoding="UTF-8"?>
+/// <FinalDraft DocumentType="Script" Template="No" Version="1">
+///   <Content>
+///     <Paragraph Type="Scene Heading" Number="1">
+///       <Text>EXT. Snowy Landscape</Text>
+///     </Paragraph>
+///     <Paragraph Type="Action">
+///       <Text>Swirls of snow obscure the rocky formations of a mountain (...)</Text>
+///     </Paragraph>
+///     <!-- (...) -->
+///     <Paragraph Type="Character" StartsNewPage="Yes">
+///       <Text>Shaman (V.O.)</Text>
+///     </Paragraph>
+///     <Paragraph Type="Parenthetical">
+///       <Text>(To Sintel)</Text>
+///     </Paragraph>
+///     <Paragraph Type="Dialogue">
+///       <Text>Here, take a sip.</Text>
+///     </Paragraph>
+///   </Content>
+///   <TitlePage>
+///     <Content>
+///       <Paragraph Alignment="Center">
+///         <Text>Sintel</Text>
+///       </Paragraph>
+///       <Paragraph Alignment="Center">
+///         <Text></Text>
+///       </Paragraph>
+///       <!-- (...) -->
+///     </Content>
+///   </TitlePage>
+/// </FinalDraft>
+/// ```
+pub fn format_script<W: Write>(script: &Script, output: &mut W) -> XmlResult<()> {
+    let mut writer = EmitterConfig::new().perform_indent(true).create_writer(output);
+    let mut last_page = None;
+
+    try!(writer.write(XmlEvent::start_element("FinalDraft")
+                               .attr("DocumentType", "Script")
+                               .attr("Template", "No")
+                               .attr("Version", "1")));
+    try!(writer.write(XmlEvent::start_element("Content")));
+
+    for scene in script.scenes.iter() {
+        for location in scene.iter() {
+            // the default location of a script has no heading
+            if location.name.len() > 0 {
+                let starts_new_page = match location.parts.first() {
+                    Some(part) => is_new_page(part, &mut last_page),
+                    None => false,
+                };
+                try!(format_scene_heading_paragraph(location, starts_new_page, &mut writer));
+            }
+
+            for part in location.parts.iter() {
+                let starts_new_page = is_new_page(part, &mut last_page);
+                try!(format_scene_part(part, starts_new_page, &mut writer));
+            }
+        }
+    }
+
+    try!(writer.write(XmlEvent::end_element()));
+
+    if !script.metadata.is_empty() {
+        try!(format_title_page(&script.metadata, &mut writer));
+    }
+
+    try!(writer.write(XmlEvent::end_element()));
+
+    Ok(())
+}
+
+
+use ::{Dialog, DialogPart, Location, ScenePart, Script, ScriptMetadata};
+use serialize::{format_character_cue, format_scene_heading};
+use std::io::Write;
+use xml::{EventWriter, EmitterConfig};
+use xml::writer::Result as XmlResult;
+use xml::writer::XmlEvent;
+
+/// Checks if the given `ScenePart` starts on a later page.
+fn is_new_page(part: &ScenePart, last_page: &mut Option<u32>) -> bool {
+    let page = part.page();
+
+    let is_new_page = last_page.map_or(false, |last_page| page > last_page);
+    *last_page = Some(page);
+
+    is_new_page
+}
+
+fn format_scene_part<W: Write>(part: &ScenePart, starts_new_page: bool, writer: &mut EventWriter<W>)
+    -> XmlResult<()> {
+    match part {
+        &ScenePart::Direction { ref direction, .. } => {
+            format_paragraph("Action", direction, starts_new_page, writer)
+        }
+        &ScenePart::Dialog(ref dialog) => format_dialog(dialog, starts_new_page, writer),
+        &ScenePart::DualDialog { ref left, ref right } => {
+            // the dual dialogue is wrapped in an untyped paragraph
+            let mut paragraph_event = XmlEvent::start_element("Paragraph");
+            if starts_new_page {
+                paragraph_event = paragraph_event.attr("StartsNewPage", "Yes");
+            }
+            try!(writer.write(paragraph_event));
+            try!(writer.write(XmlEvent::start_element("DualDialogue")));
+
+            try!(format_dialog(left, false, writer));
+            try!(format_dialog(right, false, writer));
+
+            try!(writer.write(XmlEvent::end_element()));
+            writer.write(XmlEvent::end_element())
+        }
+        &ScenePart::Transition { ref text, .. } => {
+            format_paragraph("Transition", text, starts_new_page, writer)
+        }
+    }
+}
+
+/// Writes a `Dialog` as character cue followed by its lines.
+fn format_dialog<W: Write>(dialog: &Dialog, starts_new_page: bool, writer: &mut EventWriter<W>)
+    -> XmlResult<()> {
+    let &Dialog { ref speaker, ref mode, ref dialog, .. } = dialog;
+
+    try!(format_paragraph("Character", &format_character_cue(speaker, mode),
+                          starts_new_page, writer));
+
+    for dialog_part in dialog.iter() {
+        match dialog_part {
+            &DialogPart::Dialog(ref dialog, _, _) => {
+                try!(format_paragraph("Dialogue", dialog, false, writer));
+            }
+            &DialogPart::Direction(ref direction, _, _) => {
+                try!(format_paragraph("Parenthetical", direction, false, writer));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the metadata as title page.
+///
+/// The fields are separated by empty paragraphs, so that they are read back
+/// as separate blocks.
+fn format_title_page<W: Write>(metadata: &ScriptMetadata, writer: &mut EventWriter<W>) -> XmlResult<()> {
+    let mut blocks: Vec<Vec<&str>> = Vec::new();
+    if let Some(ref title) = metadata.title {
+        blocks.push(vec![title]);
+    }
+    for credit in metadata.credits.iter() {
+        if credit.credit.len() > 0 {
+            blocks.push(vec![&credit.credit]);
+        }
+        if credit.authors.len() > 0 {
+            blocks.push(credit.authors.iter().map(|author| author.as_ref()).collect());
+        }
+    }
+    if let Some(ref source) = metadata.source {
+        blocks.push(vec![source]);
+    }
+    if let Some(ref contact) = metadata.contact {
+        blocks.push(contact.lines().collect());
+    }
+    if let Some(ref draft_date) = metadata.draft_date {
+        blocks.push(vec![draft_date]);
+    }
+    if let Some(ref revision) = metadata.revision {
+        blocks.push(vec![revision]);
+    }
+
+    try!(writer.write(XmlEvent::start_element("TitlePage")));
+    try!(writer.write(XmlEvent::start_element("Content")));
+
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            try!(format_title_paragraph("", writer));
+        }
+        for line in block.iter() {
+            try!(format_title_paragraph(line, writer));
+        }
+    }
+
+    try!(writer.write(XmlEvent::end_element()));
+    writer.write(XmlEvent::end_element())
+}
+
+fn format_title_paragraph<W: Write>(text: &str, writer: &mut EventWriter<W>) -> XmlResult<()> {
+    try!(writer.write(XmlEvent::start_element("Paragraph").attr("Alignment", "Center")));
+
+    try!(writer.write(XmlEvent::start_element("Text")));
+    try!(writer.write(XmlEvent::characters(text)));
+    try!(writer.write(XmlEvent::end_element()));
+
+    writer.write(XmlEvent::end_element())
+}
+
+/// Writes the heading of a location together with its scene number.
+fn format_scene_heading_paragraph<W: Write>(location: &Location, starts_new_page: bool,
+                                           writer: &mut EventWriter<W>) -> XmlResult<()> {
+    let mut paragraph_event = XmlEvent::start_element("Paragraph").attr("Type", "Scene Heading");
+    if let Some(ref number) = location.number {
+        paragraph_event = paragraph_event.attr("Number", number);
+    }
+    if starts_new_page {
+        paragraph_event = paragraph_event.attr("StartsNewPage", "Yes");
+    }
+    try!(writer.write(paragraph_event));
+
+    try!(writer.write(XmlEvent::start_element("Text")));
+    try!(writer.write(XmlEvent::characters(&format_scene_heading(location))));
+    try!(writer.write(XmlEvent::end_element()));
+
+    writer.write(XmlEvent::end_element())
+}
+
+fn format_paragraph<W: Write>(kind: &str, text: &str, starts_new_page: bool, writer: &mut EventWriter<W>)
+    -> XmlResult<()> {
+    let mut paragraph_event = XmlEvent::start_element("Paragraph").attr("Type", kind);
+    if starts_new_page {
+        paragraph_event = paragraph_event.attr("StartsNewPage", "Yes");
+    }
+    try!(writer.write(paragraph_event));
+
+    try!(writer.write(XmlEvent::start_element("Text")));
+    try!(writer.write(XmlEvent::characters(text)));
+    try!(writer.write(XmlEvent::end_element()));
+
+    writer.write(XmlEvent::end_element())
+}
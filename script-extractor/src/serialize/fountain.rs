@@ -0,0 +1,268 @@
+//! Serialize `Script`s to `fountain`
+
+/// Serialize the given `Script` into `fountain`
+///
+/// Serialize the given `Script` into the [Fountain] plain-text format and
+/// write it to the given `Writer`. The metadata is written as title page.
+/// Only the transitions of the script are written, so scenes without one
+/// follow each other directly, and pages are separated by page breaks
+/// (`===`). The cue of the right dialog of a dual dialog is marked with `^`. A dialog without a character cue, which can be
+/// extracted from a pdf, is written as action.
+///
+/// [Fountain]: https://fountain.io/syntax
+///
+/// # Examples
+///
+/// ```
+/// # use script_extractor::ScenePart;
+/// # use script_extractor::parse::fountain;
+/// # use script_extractor::serialize::fountain::format_script;
+/// let input = "INT. CAVE - NIGHT\n\nSHAMAN\n(softly)\nRest now.\n";
+/// let mut script = fountain::parse_script(&mut input.as_bytes()).unwrap();
+/// if let ScenePart::Dialog(ref mut dialog) = script.scenes[0][0].parts[0] {
+///     dialog.speaker.clear();
+/// }
+///
+/// let mut output = Vec::new();
+/// format_script(&script, &mut output).unwrap();
+/// let script = fountain::parse_script(&mut &output[..]).unwrap();
+/// match script.scenes[0][0].parts[0] {
+///     ScenePart::Direction { ref direction, .. } => assert_eq!(direction, "(softly) Rest now."),
+///     ref part => panic!("expected a direction, found {:?}", part),
+/// }
+/// ```
+///
+/// The output looks like this:
+///
+/// ```text
+/// Title: Sintel
+/// Credit: Written by
+/// Author: Esther Wouda
+/// Draft date: August 2010
+///
+/// EXT. Snowy Landscape
+///
+/// Swirls of snow obscure the rocky formations of a mountain (...)
+///
+/// Five ragged men attack a young girl, SINTEL, (...)
+///
+/// @Shaman
+/// You’re lucky to be alive. (...)
+///
+/// ===
+///
+/// Finally she collapses into the snow, her eyes shut tight.
+///
+/// !BLACK
+///
+/// @Shaman (V.O.)
+/// (To Sintel)
+/// Here, take a sip.
+///
+/// > FADE OUT.
+/// ```
+pub fn format_script<W: Write>(script: &Script, output: &mut W) -> io::Result<()> {
+    let mut last_page = None;
+
+    if !script.metadata.is_empty() {
+        try!(format_title_page(&script.metadata, output));
+    }
+
+    for scene in script.scenes.iter() {
+        for location in scene.iter() {
+            // break the page before the heading, if the location starts on a new page
+            if let Some(part) = location.parts.first() {
+                try!(format_page_break(part, &mut last_page, output));
+            }
+            try!(format_location_heading(location, output));
+
+            for part in location.parts.iter() {
+                try!(format_page_break(part, &mut last_page, output));
+                try!(format_scene_part(part, output));
+            }
+        }
+    }
+
+    output.flush()
+}
+
+
+use ::{Dialog, DialogPart, Location, LocationType, ScenePart, Script, ScriptMetadata};
+use serialize::{format_character_cue, format_scene_heading};
+use std::io::{self, Write};
+
+/// Writes the metadata as `key: value` pairs of the title page.
+///
+/// Values with several lines are indented on the lines after their key.
+fn format_title_page<W: Write>(metadata: &ScriptMetadata, output: &mut W) -> io::Result<()> {
+    let mut entries: Vec<(&str, Vec<&str>)> = Vec::new();
+    if let Some(ref title) = metadata.title {
+        entries.push(("Title", vec![title]));
+    }
+    for credit in metadata.credits.iter() {
+        if credit.credit.len() > 0 {
+            entries.push(("Credit", vec![&credit.credit]));
+        }
+        if credit.authors.len() > 0 {
+            let key = if credit.authors.len() > 1 { "Authors" } else { "Author" };
+            entries.push((key, credit.authors.iter().map(|author| author.as_ref()).collect()));
+        }
+    }
+    if let Some(ref source) = metadata.source {
+        entries.push(("Source", vec![source]));
+    }
+    if let Some(ref draft_date) = metadata.draft_date {
+        entries.push(("Draft date", vec![draft_date]));
+    }
+    if let Some(ref revision) = metadata.revision {
+        entries.push(("Revision", vec![revision]));
+    }
+    if let Some(ref contact) = metadata.contact {
+        entries.push(("Contact", contact.lines().collect()));
+    }
+
+    for (key, values) in entries {
+        if values.len() == 1 {
+            try!(write!(output, "{}: {}\n", key, values[0]));
+        } else {
+            try!(write!(output, "{}:\n", key));
+            for value in values {
+                try!(write!(output, "    {}\n", value));
+            }
+        }
+    }
+
+    write!(output, "\n")
+}
+
+/// Writes a page break if the given `ScenePart` starts on a later page.
+fn format_page_break<W: Write>(part: &ScenePart, last_page: &mut Option<u32>, output: &mut W)
+    -> io::Result<()> {
+    let page = part.page();
+
+    let is_new_page = last_page.map_or(false, |last_page| page > last_page);
+    *last_page = Some(page);
+
+    if is_new_page {
+        write!(output, "===\n\n")
+    } else {
+        Ok(())
+    }
+}
+
+fn format_location_heading<W: Write>(location: &Location, output: &mut W) -> io::Result<()> {
+    match location.kind {
+        // force the heading, unless this is the default location
+        LocationType::Undefined if location.name.len() > 0 => try!(write!(output, ".")),
+        LocationType::Undefined => return Ok(()),
+        _ => {}
+    }
+
+    try!(write!(output, "{}", format_scene_heading(location)));
+    if let Some(ref number) = location.number {
+        try!(write!(output, " #{}#", number));
+    }
+
+    write!(output, "\n\n")
+}
+
+fn format_scene_part<W: Write>(part: &ScenePart, output: &mut W) -> io::Result<()> {
+    match part {
+        &ScenePart::Direction { ref direction, .. } => {
+            if needs_forced_action(direction) {
+                try!(write!(output, "!"));
+            }
+            write!(output, "{}\n\n", direction)
+        }
+        &ScenePart::Dialog(ref dialog) => format_dialog(dialog, false, output),
+        &ScenePart::DualDialog { ref left, ref right } => {
+            // the right dialog can only be marked if the left one has a cue
+            try!(format_dialog(left, false, output));
+            format_dialog(right, has_cue(left), output)
+        }
+        &ScenePart::Transition { ref text, .. } => {
+            // only uppercase lines ending with `TO:` are recognized without `>`
+            let is_uppercase = text.to_uppercase() == *text;
+            if !is_uppercase || !text.ends_with("TO:") {
+                try!(write!(output, "> "));
+            }
+            write!(output, "{}\n\n", text)
+        }
+    }
+}
+
+/// Writes a `Dialog` as character cue followed by its lines.
+///
+/// The cue of the second dialog of a dual dialog is marked with `^`. A
+/// dialog without cue is written as action, since its lines would be read
+/// as action anyway.
+fn format_dialog<W: Write>(dialog: &Dialog, is_dual: bool, output: &mut W) -> io::Result<()> {
+    if !has_cue(dialog) {
+        return format_dialog_as_action(dialog, output);
+    }
+
+    let &Dialog { ref speaker, ref mode, ref dialog, .. } = dialog;
+
+    // force the character cue if it is not in uppercase
+    if speaker.to_uppercase() != *speaker {
+        try!(write!(output, "@"));
+    }
+    try!(write!(output, "{}", format_character_cue(speaker, mode)));
+    if is_dual {
+        try!(write!(output, " ^"));
+    }
+    try!(write!(output, "\n"));
+
+    for dialog_part in dialog.iter() {
+        match dialog_part {
+            &DialogPart::Dialog(ref dialog, _, _) => {
+                try!(write!(output, "{}\n", dialog));
+            }
+            &DialogPart::Direction(ref direction, _, _) => {
+                if direction.starts_with('(') {
+                    try!(write!(output, "{}\n", direction));
+                } else {
+                    try!(write!(output, "({})\n", direction));
+                }
+            }
+        }
+    }
+
+    write!(output, "\n")
+}
+
+/// Writes the lines of a `Dialog` without cue as one paragraph of action,
+/// keeping the parentheses of its directions.
+fn format_dialog_as_action<W: Write>(dialog: &Dialog, output: &mut W) -> io::Result<()> {
+    let lines: Vec<String> = dialog.dialog.iter().map(|dialog_part| match dialog_part {
+        &DialogPart::Dialog(ref dialog, _, _) => dialog.clone(),
+        &DialogPart::Direction(ref direction, _, _) if direction.starts_with('(') => direction.clone(),
+        &DialogPart::Direction(ref direction, _, _) => format!("({})", direction),
+    }).collect();
+    let action = lines.join(" ");
+
+    if action.is_empty() {
+        return Ok(());
+    }
+    if needs_forced_action(&action) {
+        try!(write!(output, "!"));
+    }
+    write!(output, "{}\n\n", action)
+}
+
+fn has_cue(dialog: &Dialog) -> bool {
+    dialog.speaker.trim().len() > 0
+}
+
+/// Checks if the given action would be mistaken for another element.
+///
+/// Uppercase lines could be parsed as scene headings, transitions or
+/// character cues and some characters at the start of a line have a special
+/// meaning.
+fn needs_forced_action(direction: &str) -> bool {
+    let is_uppercase = direction.chars().any(|c| c.is_alphabetic()) &&
+                       direction.to_uppercase() == direction;
+    let starts_with_marker = direction.starts_with(|c| ".!@>#=~".contains(c));
+
+    is_uppercase || starts_with_marker
+}
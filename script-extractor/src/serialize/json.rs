@@ -3,146 +3,147 @@
 /// Serialize the given `Script` into a `json`
 ///
 /// Serialize the given `Script` into the following `json` format and write
-/// it to the given `Writer`.
+/// it to the given `Writer`. Only the known fields of the metadata are
+/// written. The vertical positions (`top`) of the parts are only known for
+/// scripts extracted from pdfs, and the ends of a part are only written if
+/// they differ from its start.
+///
+/// The `SourceSpan`s of the parts are written as `source`, if they are
+/// known. A dialog text with `SourceSpan`s is written as an object like
+/// `{ "text": "Here, take a sip.", "source": [...] }` instead of a string.
+/// In the same way, the `Emphasis` of directions and dialog texts is written
+/// as `emphasis`, like `[{ "style": "bold", "start": 0, "end": 5 }]`.
+///
+/// The `Speaker`s of a dialog are written as `speakers`, like
+/// `[{ "name": "WOODY" }, { "name": "BUZZ" }]`, if the cue names several
+/// characters or a group, like `{ "name": "CROWD", "group": true }`. They
+/// are ignored when reading, since they are split from the `character`.
+///
+/// A dual dialog is written as `{ "dual_dialog": [...] }` containing its left
+/// and right dialog.
+///
+/// The other formats based on serde, like `yaml`, use the same structure.
 ///
 /// # Example
 ///
 /// ```json
-/// [
-///     [
-///         {
-///             "place": "Snowy Landscape",
-///             "type": "external",
-///             "parts": [{
-///                 "page": 1,
-///                 "direction": "Swirls of snow obscure the rocky formations of a mountain (...)"
-///             },{
-///                 "page": 1,
-///                 "direction": "Five ragged men attack a young girl, SINTEL, (...)"
-///             },{
-///                 "page": 1,
-///                 "character": "Shaman",
-///                 "dialog": [
-///                     "You’re lucky to be alive. (...)"
-///                 ]
-///             },{
-///                 "page": 2,
-///                 "direction": "Finally she collapses into the snow, her eyes shut tight."
-///             },{
-///                 "page": 2,
-///                 "direction": "BLACK"
-///             },{
-///                 "page": 2,
-///                 "character": "Shaman",
-///                 "mode": "VO",
-///                 "dialog": [
-///                     { "direction": "(To Sintel)" },
-///                     "Here, take a sip."
-///                 ]
-///             }]
-///         }
+/// {
+///     "metadata": {
+///         "title": "Sintel",
+///         "credits": [
+///             { "credit": "Written by", "authors": ["Esther Wouda"] }
+///         ],
+///         "draft_date": "August 2010"
+///     },
+///     "scenes": [
+///         [
+///             {
+///                 "place": "Snowy Landscape - Day",
+///                 "type": "external",
+///                 "number": "1",
+///                 "primary_place": "Snowy Landscape",
+///                 "time_of_day": "DAY",
+///                 "parts": [{
+///                     "page": 1,
+///                     "top": 130,
+///                     "end_top": 145,
+///                     "direction": "Swirls of snow obscure the rocky formations of a mountain (...)"
+///                 },{
+///                     "page": 1,
+///                     "end_page": 2,
+///                     "top": 985,
+///                     "end_top": 100,
+///                     "direction": "Five ragged men attack a young girl, SINTEL, (...)"
+///                 },{
+///                     "page": 2,
+///                     "top": 130,
+///                     "end_top": 160,
+///                     "character": "Shaman",
+///                     "dialog": [
+///                         "You’re lucky to be alive. (...)"
+///                     ]
+///                 },{
+///                     "page": 2,
+///                     "top": 190,
+///                     "direction": "Finally she collapses into the snow, her eyes shut tight."
+///                 },{
+///                     "page": 2,
+///                     "top": 220,
+///                     "direction": "BLACK",
+///                     "revised": true
+///                 },{
+///                     "page": 2,
+///                     "end_page": 3,
+///                     "top": 970,
+///                     "end_top": 115,
+///                     "character": "Shaman",
+///                     "mode": "VO",
+///                     "dialog": [
+///                         { "direction": "(To Sintel)" },
+///                         "Here, take a sip."
+///                     ]
+///                 },{
+///                     "page": 3,
+///                     "top": 145,
+///                     "transition": "cut",
+///                     "text": "CUT TO:"
+///                 }]
+///             }
+///         ]
 ///     ]
-/// ]
+/// }
 /// ```
-pub fn format_script<W: Write>(scenes: &Script, output: &mut W) -> json::EncodeResult<()> {
-    let mut writer = IoFmtWriter { writer: output };
-    let mut encoder = json::Encoder::new_pretty(&mut writer);
-
-    try!(scenes.encode(&mut encoder));
-
-    Ok(())
-}
-
-
-use ::{DialogPart, Location, LocationType, ScenePart, Script};
-use rustc_serialize::Encodable;
-use rustc_serialize::{Encoder, json};
-use std::io::Write;
-use std::fmt;
-
-/// Needed because rustc-serialize uses fmt::Write instead of io::Write.
-/// See https://github.com/rust-lang-nursery/rustc-serialize/issues/111
-struct IoFmtWriter<'a> {
-    writer: &'a mut Write
+pub fn format_script<W: Write>(script: &Script, output: &mut W) -> serde_json::Result<()> {
+    try!(serde_json::to_writer_pretty(&mut *output, script));
+    output.flush().map_err(serde_json::Error::io)
 }
 
-/// Pass all writes to the underlying io::Write.
-impl<'a> fmt::Write for IoFmtWriter<'a> {
-    fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
-        if let Err(_) = self.writer.write_all(s.as_bytes()) {
-            Err(fmt::Error)
-        } else {
-            Ok(())
-        }
-    }
-}
-
-/// Flush the underlying io::Write when dropping the object.
-impl<'a> Drop for IoFmtWriter<'a> {
-    fn drop(&mut self) {
-        self.writer.flush().ok();
-    }
-}
+/// Parse a `Script` from the given `json`
+///
+/// Reads a `json` in the format written by `format_script` from `reader`
+/// and parses it back into a `Script`. Serializing a `Script` and parsing
+/// it again results in the same `Script`.
+///
+/// # Examples
+///
+/// ```
+/// # use script_extractor::parse::fountain;
+/// # use script_extractor::serialize::json;
+/// let fountain = "INT. ANDY'S ROOM - DAY\n\n\
+///                 Woody sits on the bed.\n\n\
+///                 WOODY (into phone, quietly)\n(whispering)\nStaff meeting, everyone!\n\n\
+///                 WOODY AND BUZZ\nTo infinity!\n\n\
+///                 BUZZ\nHello.\n\nREX ^\nHi!\n\n\
+///                 CUT TO:\n";
+/// let script = fountain::parse_script(&mut fountain.as_bytes()).unwrap();
+///
+/// let mut formatted = Vec::new();
+/// json::format_script(&script, &mut formatted).unwrap();
+/// let parsed = json::parse_script(&mut &formatted[..]).unwrap();
+/// let mut reformatted = Vec::new();
+/// json::format_script(&parsed, &mut reformatted).unwrap();
+///
+/// assert_eq!(String::from_utf8(formatted).unwrap(), String::from_utf8(reformatted).unwrap());
+/// ```
+pub fn parse_script(reader: &mut Read) -> Result<Script, ParseError> {
+    let mut json = String::new();
+    try!(reader.read_to_string(&mut json).map_err(|e| {
+        ParseError::Io { message: e.to_string() }
+    }));
 
-impl Encodable for Location {
-    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
-        s.emit_map(3, |s| {
-            try!(emit_map_key_val(s, 0, "place", |s| self.name.encode(s)));
-            match self.kind {
-                LocationType::Undefined => {}
-                ref kind => {
-                    let kind: &str = kind.clone().into();
-                    try!(emit_map_key_val(s, 1, "type", |s| kind.encode(s)));
-                }
-            }
-            try!(emit_map_key_val(s, 2, "parts", |s| self.parts.encode(s)));
-            Ok(())
+    // scripts without metadata used to be written as the array of scenes
+    if json.trim_start().starts_with('[') {
+        Ok(Script {
+            metadata: Default::default(),
+            scenes: try!(serde_json::from_str(&json)),
         })
+    } else {
+        Ok(try!(serde_json::from_str(&json)))
     }
 }
 
-impl Encodable for ScenePart {
-    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
-        match self {
-            &ScenePart::Direction { ref direction, ref page } => {
-                s.emit_map(2, |s| {
-                    try!(emit_map_key_val(s, 0, "page", |s| page.encode(s)));
-                    try!(emit_map_key_val(s, 1, "direction", |s| direction.encode(s)));
-                    Ok(())
-                })
-            }
-            &ScenePart::Dialog { ref speaker, ref dialog, ref page } => {
-                s.emit_map(3, |s| {
-                    try!(emit_map_key_val(s, 0, "page", |s| page.encode(s)));
-                    try!(emit_map_key_val(s, 1, "character", |s| speaker.encode(s)));
-                    try!(emit_map_key_val(s, 2, "dialog", |s| dialog.encode(s)));
-                    Ok(())
-                })
-            }
-        }
-    }
-}
 
-impl Encodable for DialogPart {
-    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
-        match self {
-            &DialogPart::Dialog(ref dialog) => {
-                s.emit_str(dialog)
-            }
-            &DialogPart::Direction(ref direction) => {
-                s.emit_map(1, |s| {
-                    emit_map_key_val(s, 0, "direction", |s| direction.encode(s))
-                })
-            }
-        }
-    }
-}
-
-/// Convenience function for emitting both key and value of a map entry
-fn emit_map_key_val<S, F>(s: &mut S, idx: usize, key: &str, f: F) -> Result<(), S::Error>
-    where S: Encoder,
-          F: FnOnce(&mut S) -> Result<(), S::Error> {
-    try!(s.emit_map_elt_key(idx, |s| key.encode(s)));
-    try!(s.emit_map_elt_val(idx, f));
-    Ok(())
-}
+use ::Script;
+use parse::ParseError;
+use serde_json;
+use std::io::{Read, Write};
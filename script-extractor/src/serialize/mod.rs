@@ -1,4 +1,100 @@
 //! Serialize `Script`s into different formats
 
+pub mod cbor;
+pub mod fdx;
+pub mod fountain;
 pub mod json;
+pub mod msgpack;
+pub mod ron;
 pub mod xml;
+pub mod yaml;
+
+mod model;
+
+use ::{DialogMode, Location, LocationType, Speaker, TimeOfDay};
+
+/// Joins the extensions of a `Dialog` into a comma separated string.
+///
+/// This is the representation of the `mode` of a `Dialog` used by all
+/// formats. Commas and backslashes within an extension, like in
+/// `(into phone, quietly)`, are escaped by a backslash.
+fn format_mode(mode: &Vec<DialogMode>) -> String {
+    mode.iter()
+        .map(|m| m.as_str().replace('\\', "\\\\").replace(',', "\\,"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Splits the `mode` of a `Dialog` back into its extensions.
+///
+/// This is the inverse of `format_mode`.
+fn parse_mode(mode: &str) -> Vec<DialogMode> {
+    let mut extensions = vec![String::new()];
+    let mut chars = mode.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => extensions.last_mut().unwrap().extend(chars.next()),
+            ',' => extensions.push(String::new()),
+            _ => extensions.last_mut().unwrap().push(c),
+        }
+    }
+
+    extensions.iter()
+              .filter(|extension| extension.len() > 0)
+              .map(|extension| DialogMode::from_extension(extension))
+              .collect()
+}
+
+/// Parses the `time_of_day` of a `Location` written using `TimeOfDay::as_str`.
+///
+/// Unknown times are kept as `Other`.
+fn parse_time_of_day(time_of_day: &str) -> TimeOfDay {
+    TimeOfDay::from_text(time_of_day).unwrap_or_else(|| TimeOfDay::Other(time_of_day.to_string()))
+}
+
+/// Checks if the speaker of a `Dialog` is a single character.
+///
+/// The `speakers` are only written for the other dialogs. They are never
+/// read, since they are split from the speaker again.
+fn is_single_speaker(speaker: &str, speakers: &Vec<Speaker>) -> bool {
+    match speakers.first() {
+        Some(&Speaker::Character(ref name)) => speakers.len() == 1 && name == speaker,
+        Some(&Speaker::Group(_)) => false,
+        None => speaker.is_empty(),
+    }
+}
+
+/// Converts the `DialogMode` into the extension commonly used in scripts.
+fn format_extension(mode: &DialogMode) -> &str {
+    match mode {
+        &DialogMode::VoiceOver => "V.O.",
+        &DialogMode::OffScreen => "O.S.",
+        &DialogMode::OffCamera => "O.C.",
+        &DialogMode::Continued => "CONT'D",
+        &DialogMode::Other(ref extension) => extension,
+    }
+}
+
+/// Rebuilds the character cue of a `Dialog` from its speaker and mode.
+fn format_character_cue(speaker: &str, mode: &Vec<DialogMode>) -> String {
+    let mut cue = speaker.to_string();
+    for extension in mode.iter() {
+        cue.push_str(&format!(" ({})", format_extension(extension)));
+    }
+    cue
+}
+
+/// Rebuilds the scene heading of a `Location` from its kind and name.
+///
+/// Locations with an `Undefined` kind only consist of the name.
+fn format_scene_heading(location: &Location) -> String {
+    let prefix = match location.kind {
+        LocationType::Internal => "INT. ",
+        LocationType::External => "EXT. ",
+        LocationType::InternalExternal => "INT./EXT. ",
+        LocationType::Establishing => "EST. ",
+        LocationType::Undefined => "",
+    };
+
+    format!("{}{}", prefix, location.name)
+}
@@ -0,0 +1,370 @@
+//! The serde representation of the parts of a `Script`.
+//!
+//! Most types of the `Script` derive their serde implementations, but the
+//! `ScenePart`s, `Dialog`s and `DialogPart`s leave out the fields which can be
+//! derived from others, like the `end_page` of a part ending on its first
+//! page. These are serialized through borrowing structs with the optional
+//! fields and filled in again when deserializing.
+
+
+use ::{Dialog, DialogMode, DialogPart, Emphasis, LocationType, ScenePart, SourceSpan, Speaker, TimeOfDay, TransitionKind};
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{Error, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serialize::{format_mode, is_single_speaker, parse_mode, parse_time_of_day};
+use std::borrow::Cow;
+use std::fmt;
+
+/// The fields of all kinds of `ScenePart`s in the order they are written.
+///
+/// A part is a direction, dialog, dual dialog or transition depending on
+/// which of `direction`, `character`, `dual_dialog` and `transition` is set.
+#[derive(Default, Serialize, Deserialize)]
+struct ScenePartFields<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_top: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direction: Option<Cow<'a, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    character: Option<Cow<'a, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mode: Option<Cow<'a, str>>,
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    speakers: Option<Cow<'a, [Speaker]>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dialog: Option<Cow<'a, [DialogPart]>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dual_dialog: Option<(Cow<'a, Dialog>, Cow<'a, Dialog>)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transition: Option<TransitionKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<Cow<'a, str>>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    revised: bool,
+    #[serde(default, skip_serializing_if = "is_empty")]
+    source: Cow<'a, [SourceSpan]>,
+    #[serde(default, skip_serializing_if = "is_empty")]
+    emphasis: Cow<'a, [Emphasis]>,
+}
+
+/// The fields of a `DialogPart` which is not written as a plain string.
+///
+/// A part is a direction if `direction` is set and dialog otherwise.
+#[derive(Default, Serialize, Deserialize)]
+struct DialogPartFields<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<Cow<'a, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direction: Option<Cow<'a, str>>,
+    #[serde(default, skip_serializing_if = "is_empty")]
+    source: Cow<'a, [SourceSpan]>,
+    #[serde(default, skip_serializing_if = "is_empty")]
+    emphasis: Cow<'a, [Emphasis]>,
+}
+
+/// Reads a `DialogPart` from either a plain string or its fields.
+struct DialogPartVisitor;
+
+/// Groups are marked with `group`, which is left out for characters.
+#[derive(Serialize, Deserialize)]
+struct SpeakerFields<'a> {
+    name: Cow<'a, str>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    group: bool,
+}
+
+impl<'a> ScenePartFields<'a> {
+    /// Collects the fields of the given part.
+    ///
+    /// The end is only set if it differs from the start and the positions
+    /// only if they are known.
+    fn new(part: &'a ScenePart) -> ScenePartFields<'a> {
+        match part {
+            &ScenePart::Direction { ref direction, page, end_page, top, end_top, revised, ref source, ref emphasis } => {
+                ScenePartFields {
+                    direction: Some(Cow::Borrowed(direction)),
+                    revised: revised,
+                    source: Cow::Borrowed(source),
+                    emphasis: Cow::Borrowed(emphasis),
+                    ..ScenePartFields::with_position(page, end_page, top, end_top)
+                }
+            }
+            &ScenePart::Dialog(ref dialog) => ScenePartFields::from_dialog(dialog),
+            &ScenePart::DualDialog { ref left, ref right } => {
+                ScenePartFields {
+                    dual_dialog: Some((Cow::Borrowed(left), Cow::Borrowed(right))),
+                    ..Default::default()
+                }
+            }
+            &ScenePart::Transition { ref kind, ref text, page, end_page, top, end_top, ref source } => {
+                ScenePartFields {
+                    transition: Some(*kind),
+                    text: Some(Cow::Borrowed(text)),
+                    source: Cow::Borrowed(source),
+                    ..ScenePartFields::with_position(page, end_page, top, end_top)
+                }
+            }
+        }
+    }
+
+    /// Collects the fields of the given dialog, which is written like a
+    /// `ScenePart::Dialog` both on its own and within a dual dialog.
+    fn from_dialog(dialog: &'a Dialog) -> ScenePartFields<'a> {
+        let speakers = dialog.speakers();
+        let &Dialog { ref speaker, ref mode, ref dialog, page, end_page, top, end_top, revised, ref source } = dialog;
+
+        ScenePartFields {
+            character: Some(Cow::Borrowed(speaker)),
+            mode: if mode.len() > 0 { Some(Cow::Owned(format_mode(mode))) } else { None },
+            speakers: if is_single_speaker(speaker, &speakers) { None } else { Some(Cow::Owned(speakers)) },
+            dialog: Some(Cow::Borrowed(dialog)),
+            revised: revised,
+            source: Cow::Borrowed(source),
+            ..ScenePartFields::with_position(page, end_page, top, end_top)
+        }
+    }
+
+    fn with_position(page: u32, end_page: u32, top: Option<i32>, end_top: Option<i32>) -> ScenePartFields<'a> {
+        ScenePartFields {
+            page: Some(page),
+            end_page: if end_page != page { Some(end_page) } else { None },
+            top: top,
+            end_top: end_top.and_then(|end_top| {
+                if end_page != page || Some(end_top) != top { Some(end_top) } else { None }
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Builds the part from the fields, using the start for a missing end
+    /// and ignoring the `speakers`, which are split from the character cue.
+    fn into_part(self) -> Result<ScenePart, String> {
+        if let Some((left, right)) = self.dual_dialog {
+            return Ok(ScenePart::DualDialog {
+                left: left.into_owned(),
+                right: right.into_owned(),
+            });
+        }
+        if self.character.is_some() {
+            return self.into_dialog().map(ScenePart::Dialog);
+        }
+
+        let page = try!(self.page.ok_or_else(|| invalid_structure("page", "a number")));
+        let end_page = self.end_page.unwrap_or(page);
+        let end_top = self.end_top.or(self.top);
+
+        if let Some(direction) = self.direction {
+            Ok(ScenePart::Direction {
+                direction: direction.into_owned(),
+                page: page,
+                end_page: end_page,
+                top: self.top,
+                end_top: end_top,
+                revised: self.revised,
+                source: self.source.into_owned(),
+                emphasis: self.emphasis.into_owned(),
+            })
+        } else if let Some(kind) = self.transition {
+            Ok(ScenePart::Transition {
+                kind: kind,
+                text: self.text.map(|text| text.into_owned()).unwrap_or(String::new()),
+                page: page,
+                end_page: end_page,
+                top: self.top,
+                end_top: end_top,
+                source: self.source.into_owned(),
+            })
+        } else {
+            Err(invalid_structure("part", "a direction, a dialog, a dual dialog or a transition"))
+        }
+    }
+
+    /// Builds the dialog from the fields like `into_part`.
+    fn into_dialog(self) -> Result<Dialog, String> {
+        let speaker = try!(self.character.ok_or_else(|| invalid_structure("character", "a string")));
+        let page = try!(self.page.ok_or_else(|| invalid_structure("page", "a number")));
+        let dialog = try!(self.dialog.ok_or_else(|| invalid_structure("dialog", "an array")));
+
+        Ok(Dialog {
+            speaker: speaker.into_owned(),
+            mode: self.mode.map(|mode| parse_mode(&mode)).unwrap_or(Vec::new()),
+            dialog: dialog.into_owned(),
+            page: page,
+            end_page: self.end_page.unwrap_or(page),
+            top: self.top,
+            end_top: self.end_top.or(self.top),
+            revised: self.revised,
+            source: self.source.into_owned(),
+        })
+    }
+}
+
+impl Serialize for ScenePart {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ScenePartFields::new(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ScenePart {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ScenePart, D::Error> {
+        let fields = try!(ScenePartFields::deserialize(deserializer));
+        fields.into_part().map_err(D::Error::custom)
+    }
+}
+
+/// Written like a `ScenePart::Dialog`, so that the dialogs of a dual dialog
+/// cannot be any other part.
+impl Serialize for Dialog {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ScenePartFields::from_dialog(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Dialog {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Dialog, D::Error> {
+        let fields = try!(ScenePartFields::deserialize(deserializer));
+        fields.into_dialog().map_err(D::Error::custom)
+    }
+}
+
+/// Dialog is written as a plain string, unless it carries `SourceSpan`s or
+/// `Emphasis`.
+impl Serialize for DialogPart {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fields = match self {
+            &DialogPart::Dialog(ref text, ref source, ref emphasis) => {
+                if source.is_empty() && emphasis.is_empty() {
+                    return serializer.serialize_str(text);
+                }
+                DialogPartFields { text: Some(Cow::Borrowed(text)), ..DialogPartFields::new(source, emphasis) }
+            }
+            &DialogPart::Direction(ref direction, ref source, ref emphasis) => {
+                DialogPartFields { direction: Some(Cow::Borrowed(direction)), ..DialogPartFields::new(source, emphasis) }
+            }
+        };
+
+        fields.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DialogPart {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<DialogPart, D::Error> {
+        deserializer.deserialize_any(DialogPartVisitor)
+    }
+}
+
+impl<'a> DialogPartFields<'a> {
+    fn new(source: &'a [SourceSpan], emphasis: &'a [Emphasis]) -> DialogPartFields<'a> {
+        DialogPartFields {
+            source: Cow::Borrowed(source),
+            emphasis: Cow::Borrowed(emphasis),
+            ..Default::default()
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for DialogPartVisitor {
+    type Value = DialogPart;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string, a text or a direction")
+    }
+
+    fn visit_str<E: Error>(self, text: &str) -> Result<DialogPart, E> {
+        Ok(DialogPart::Dialog(text.to_string(), Vec::new(), Vec::new()))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<DialogPart, A::Error> {
+        let fields = try!(DialogPartFields::deserialize(MapAccessDeserializer::new(map)));
+        let (source, emphasis) = (fields.source.into_owned(), fields.emphasis.into_owned());
+
+        match (fields.text, fields.direction) {
+            (_, Some(direction)) => Ok(DialogPart::Direction(direction.into_owned(), source, emphasis)),
+            (Some(text), None) => Ok(DialogPart::Dialog(text.into_owned(), source, emphasis)),
+            (None, None) => Err(A::Error::custom(invalid_structure("dialog part", "a string, a text or a direction"))),
+        }
+    }
+}
+
+impl Serialize for Speaker {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SpeakerFields {
+            name: Cow::Borrowed(self.name()),
+            group: self.is_group(),
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Speaker {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Speaker, D::Error> {
+        let fields = try!(SpeakerFields::deserialize(deserializer));
+        if fields.group {
+            Ok(Speaker::Group(fields.name.into_owned()))
+        } else {
+            Ok(Speaker::Character(fields.name.into_owned()))
+        }
+    }
+}
+
+/// Written as string, like `internal,external`, which is a valid value in
+/// all formats unlike an identifier.
+impl Serialize for LocationType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let kind: &str = self.clone().into();
+        serializer.serialize_str(kind)
+    }
+}
+
+impl<'de> Deserialize<'de> for LocationType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<LocationType, D::Error> {
+        let kind = try!(String::deserialize(deserializer));
+        kind.parse().map_err(|_| D::Error::custom(invalid_structure("type", "a location type")))
+    }
+}
+
+/// Written like in the scene heading, unknown times are kept as `Other`.
+impl Serialize for TimeOfDay {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeOfDay {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<TimeOfDay, D::Error> {
+        let time_of_day = try!(String::deserialize(deserializer));
+        Ok(parse_time_of_day(&time_of_day))
+    }
+}
+
+/// Written using `DialogMode::as_str`, unknown extensions are kept as
+/// `Other`.
+impl Serialize for DialogMode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for DialogMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<DialogMode, D::Error> {
+        let extension = try!(String::deserialize(deserializer));
+        Ok(DialogMode::from_extension(&extension))
+    }
+}
+
+fn invalid_structure(element: &str, expected: &str) -> String {
+    format!("expected {} to be {}", element, expected)
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_empty<T: Clone>(items: &Cow<[T]>) -> bool {
+    items.is_empty()
+}
@@ -0,0 +1,20 @@
+//! Serialize `Script`s to `MessagePack`
+
+/// Serialize the given `Script` into `MessagePack`
+///
+/// Serialize the given `Script` into the binary [MessagePack] format with
+/// the same structure as the `json` written by
+/// `serialize::json::format_script`. Structs are written as maps with the
+/// names of their fields as keys instead of arrays, as fields which are not
+/// known are left out.
+///
+/// [MessagePack]: https://msgpack.org
+pub fn format_script<W: Write>(script: &Script, output: &mut W) -> io::Result<()> {
+    try!(rmp_serde::encode::write_named(output, script).map_err(|e| io::Error::new(io::ErrorKind::Other, e)));
+    output.flush()
+}
+
+
+use ::Script;
+use rmp_serde;
+use std::io::{self, Write};
@@ -0,0 +1,81 @@
+//! Serialize `Script`s to `ron`
+
+/// Serialize the given `Script` into `ron`
+///
+/// Serialize the given `Script` into the [Rusty Object Notation] with the
+/// same structure as the `json` written by `serialize::json::format_script`.
+/// Optional fields are written without `Some` using the `implicit_some`
+/// extension.
+///
+/// [Rusty Object Notation]: https://github.com/ron-rs/ron
+///
+/// # Examples
+///
+/// ```
+/// # extern crate ron;
+/// # extern crate script_extractor;
+/// # use script_extractor::{LocationType, Script};
+/// # use script_extractor::parse::fountain;
+/// # use script_extractor::serialize::ron::format_script;
+/// # fn main() {
+/// let input = "INT./EXT. CAR - DAY\n\nWoody drives.\n";
+/// let script = fountain::parse_script(&mut input.as_bytes()).unwrap();
+///
+/// let mut output = Vec::new();
+/// format_script(&script, &mut output).unwrap();
+/// let output = String::from_utf8(output).unwrap();
+/// assert!(output.contains("type: \"internal,external\""));
+///
+/// let script: Script = ron::de::from_str(&output).unwrap();
+/// assert_eq!(script.scenes[0][0].kind, LocationType::InternalExternal);
+/// # }
+/// ```
+///
+/// The structure of the output looks like this:
+///
+///
+/// ```text
+/// #![enable(implicit_some)]
+/// (
+///     metadata: (
+///         title: "Sintel",
+///         credits: [
+///             (
+///                 credit: "Written by",
+///                 authors: [
+///                     "Esther Wouda",
+///                 ],
+///             ),
+///         ],
+///     ),
+///     scenes: [
+///         [
+///             (
+///                 place: "Snowy Landscape - Day",
+///                 type: "external",
+///                 parts: [
+///                     (
+///                         page: 3,
+///                         top: 145,
+///                         transition: cut,
+///                         text: "CUT TO:",
+///                     ),
+///                 ],
+///             ),
+///         ],
+///     ],
+/// )
+/// ```
+pub fn format_script<W: Write>(script: &Script, output: &mut W) -> io::Result<()> {
+    let config = PrettyConfig::new().extensions(Extensions::IMPLICIT_SOME);
+    try!(ron::ser::to_writer_pretty(&mut *output, script, config).map_err(|e| io::Error::new(io::ErrorKind::Other, e)));
+    try!(writeln!(output));
+    output.flush()
+}
+
+
+use ::Script;
+use ron;
+use ron::extensions::Extensions;
+use ron::ser::PrettyConfig;
+use std::io::{self, Write};
@@ -3,36 +3,69 @@
 /// Serialize the given `Script` into a `xml`
 ///
 /// Serialize the given `Script` into the following `xml` format and write
-/// it to the given `Writer`.
+/// it to the given `Writer`. The metadata is only written if it is known,
+/// with the `<author>`s of each credit following its `<credit>`. The
+/// vertical positions (`top`) of the parts are only known for scripts
+/// extracted from pdfs, and the ends of a part are only written if they
+/// differ from its start.
+///
+/// The `SourceSpan`s of the parts are written as `<source_span>` elements
+/// at the start of a part, if they are known. A dialog text with
+/// `SourceSpan`s is wrapped into a `<text>` element, like
+/// `<text><source_span page="2" top="985" left="180" bottom="1000" right="480" lines="57"/>Here, take a sip.</text>`.
+/// The `Emphasis` of directions and dialog texts is written as `<b>` and
+/// `<em>` elements within the text, like `Here, <em>take</em> a sip.`, and
+/// dialog texts with `Emphasis` are wrapped into `<text>` elements as well.
+///
+/// The `Speaker`s of a dialog naming several characters or a group are
+/// written as `<speaker>` elements at its start, like
+/// `<speaker name="WOODY"/><speaker name="BUZZ"/>` or
+/// `<speaker name="CROWD" group="true"/>`. They are ignored when reading,
+/// since they are split from the `character`.
+///
+/// A dual dialog is written as `<dual_dialog>` element containing its left
+/// and right `<dialog>`.
 ///
 /// # Example
 ///
 /// ```xml
 /// <?xml version="1.0" encoding="UTF-8"?>
 /// <script>
+///   <metadata>
+///     <title>Sintel</title>
+///     <credit>Written by</credit>
+///     <author>Esther Wouda</author>
+///     <draft_date>August 2010</draft_date>
+///   </metadata>
 ///   <scene>
-///     <location place="Snowy Landscape" type="external">
-///       <direction page="1">Swirls of snow obscure the rocky formations of a mountain (...)</direction>
-///       <direction page="1">Five ragged men attack a young girl, SINTEL, (...)</direction>
+///     <location place="Snowy Landscape - Day" type="external" number="1"
+///               primary_place="Snowy Landscape" time_of_day="DAY">
+///       <direction page="1" top="130" end_top="145">Swirls of snow obscure the rocky formations of a mountain (...)</direction>
+///       <direction page="1" end_page="2" top="985" end_top="100">Five ragged men attack a young girl, SINTEL, (...)</direction>
 ///       <!-- (...) -->
-///       <dialog character="Shaman" page="1">You’re lucky to be alive. (...)</dialog>
-///       <direction page="2">Finally she collapses into the snow, her eyes shut tight.</direction>
-///       <direction page="2">BLACK</direction>
-///       <dialog character="Shaman" mode="VO" page="2">
+///       <dialog character="Shaman" page="2" top="130" end_top="160">You’re lucky to be alive. (...)</dialog>
+///       <direction page="2" top="190">Finally she collapses into the snow, her eyes shut tight.</direction>
+///       <direction page="2" top="220" revised="true">BLACK</direction>
+///       <dialog character="Shaman" mode="VO" page="2" end_page="3" top="970" end_top="115">
 ///           <direction>(To Sintel)</direction>
 ///           Here, take a sip.
 ///       </dialog>
+///       <transition kind="cut" page="3" top="145">CUT TO:</transition>
 ///     </location>
 ///   </scene>
 ///   <!-- (...) -->
 /// </script>
 /// ```
-pub fn format_script<W: Write>(scenes: &Script, output: &mut W) -> XmlResult<()> {
+pub fn format_script<W: Write>(script: &Script, output: &mut W) -> XmlResult<()> {
     let mut writer = EmitterConfig::new().perform_indent(true).create_writer(output);
 
     try!(writer.write(XmlEvent::start_element("script")));
 
-    for scene in scenes.iter() {
+    if !script.metadata.is_empty() {
+        try!(format_metadata(&script.metadata, &mut writer));
+    }
+
+    for scene in script.scenes.iter() {
         try!(writer.write(XmlEvent::start_element("scene")));
 
         for location in scene.iter() {
@@ -46,6 +79,19 @@ pub fn format_script<W: Write>(scenes: &Script, output: &mut W) -> XmlResult<()>
                     location_event = location_event.attr("type", kind.clone().into());
                 }
             }
+            if let Some(ref number) = location.number {
+                location_event = location_event.attr("number", number);
+            }
+            if location.primary_place.len() > 0 {
+                location_event = location_event.attr("primary_place", &location.primary_place);
+            }
+            let sub_locations = location.sub_locations.join(" - ");
+            if sub_locations.len() > 0 {
+                location_event = location_event.attr("sub_locations", &sub_locations);
+            }
+            if let Some(ref time_of_day) = location.time_of_day {
+                location_event = location_event.attr("time_of_day", time_of_day.as_str());
+            }
             try!(writer.write(location_event));
 
             try!(format_scene_parts(&location.parts, &mut writer));
@@ -61,49 +107,648 @@ pub fn format_script<W: Write>(scenes: &Script, output: &mut W) -> XmlResult<()>
     Ok(())
 }
 
+/// Parse a `Script` from the given `xml`
+///
+/// Reads a `xml` in the format written by `format_script` from `reader`
+/// and parses it back into a `Script`. Serializing a `Script` and parsing
+/// it again results in the same `Script`, as long as its texts do not
+/// start or end with whitespace.
+///
+/// # Examples
+///
+/// ```
+/// # use script_extractor::parse::fountain;
+/// # use script_extractor::serialize::xml;
+/// let fountain = "INT. ANDY'S ROOM - DAY\n\n\
+///                 Woody sits on the bed.\n\n\
+///                 WOODY (into phone, quietly)\n(whispering)\nStaff meeting, everyone!\n\n\
+///                 WOODY AND BUZZ\nTo infinity!\n\n\
+///                 BUZZ\nHello.\n\nREX ^\nHi!\n\n\
+///                 CUT TO:\n";
+/// let script = fountain::parse_script(&mut fountain.as_bytes()).unwrap();
+///
+/// let mut formatted = Vec::new();
+/// xml::format_script(&script, &mut formatted).unwrap();
+/// let parsed = xml::parse_script(&mut &formatted[..]).unwrap();
+/// let mut reformatted = Vec::new();
+/// xml::format_script(&parsed, &mut reformatted).unwrap();
+///
+/// assert_eq!(String::from_utf8(formatted).unwrap(), String::from_utf8(reformatted).unwrap());
+/// ```
+pub fn parse_script(reader: &mut Read) -> Result<Script, ParseError> {
+    let mut script: Script = Default::default();
 
-use ::{DialogPart, LocationType, ScenePart, Script};
-use std::io::Write;
-use xml::{EventWriter, EmitterConfig};
-use xml::writer::Result as XmlResult;
-use xml::writer::XmlEvent;
+    // states for the streaming xml parsing
+    let mut current_part: Option<ScenePart> = None;
+    let mut current_text_buffer = String::new();
+    let mut in_dialog_direction = false;
+    let mut in_dialog_text = false;
+    let mut part_source: Vec<SourceSpan> = Vec::new();
+    let mut dialog_part_source: Vec<SourceSpan> = Vec::new();
+    let mut emphasis_starts: Vec<(EmphasisStyle, usize)> = Vec::new();
+    let mut current_emphasis: Vec<Emphasis> = Vec::new();
+    let mut dual_dialog: Option<Vec<Dialog>> = None;
 
-fn format_scene_parts<W: Write>(scene_parts: &Vec<ScenePart>, writer: &mut EventWriter<W>) -> XmlResult<()> {
-    for part in scene_parts.iter() {
-        match part {
-            &ScenePart::Direction { ref direction, ref page } => {
-                try!(writer.write(XmlEvent::start_element("direction")
-                                           .attr("page", page.to_string().as_ref())));
+    let parser = EventReader::new(reader);
+    for event in parser {
+        match try!(event) {
+            ReaderEvent::StartElement { name, attributes, .. } => {
+                match name.local_name.as_ref() {
+                    "script" | "metadata" => {}
+                    "title" | "credit" | "author" | "source" | "draft_date" | "revision" | "contact" => {}
+                    "scene" => script.scenes.push(Vec::new()),
+                    "location" => {
+                        let mut location: Location = Default::default();
+                        for attr in attributes {
+                            match attr.name.local_name.as_ref() {
+                                "place" => location.name = attr.value,
+                                "type" => location.kind = try!(attr.value.parse().map_err(|_| {
+                                    invalid_structure(&format!("invalid location type '{}'", attr.value))
+                                })),
+                                "number" => location.number = Some(attr.value),
+                                "primary_place" => location.primary_place = attr.value,
+                                "sub_locations" => {
+                                    location.sub_locations = attr.value.split(" - ")
+                                                                       .map(|s| s.to_string())
+                                                                       .collect();
+                                }
+                                "time_of_day" => {
+                                    location.time_of_day = Some(parse_time_of_day(&attr.value));
+                                }
+                                _ => {}
+                            }
+                        }
+
+                        let scene = try!(script.scenes.last_mut().ok_or_else(|| {
+                            invalid_structure("location outside of scene")
+                        }));
+                        scene.push(location);
+                    }
+                    "direction" => {
+                        if let Some(ScenePart::Dialog(Dialog { ref mut dialog, .. })) = current_part {
+                            // inline direction of a dialog
+                            push_dialog_text(dialog, &current_text_buffer);
+                            in_dialog_direction = true;
+                        } else {
+                            let (page, end_page, top, end_top) = try!(read_position(&attributes));
+                            current_part = Some(ScenePart::Direction {
+                                direction: String::new(),
+                                page: page,
+                                end_page: end_page,
+                                top: top,
+                                end_top: end_top,
+                                revised: try!(read_revised(&attributes)),
+                                source: Vec::new(),
+                                emphasis: Vec::new(),
+                            });
+                        }
+                    }
+                    "transition" => {
+                        let mut kind = TransitionKind::Other;
+                        for attr in attributes.iter() {
+                            if attr.name.local_name == "kind" {
+                                kind = try!(attr.value.parse().map_err(|_| {
+                                    invalid_structure(&format!("invalid transition kind '{}'", attr.value))
+                                }));
+                            }
+                        }
 
-                try!(writer.write(XmlEvent::characters(direction)));
+                        let (page, end_page, top, end_top) = try!(read_position(&attributes));
+                        current_part = Some(ScenePart::Transition {
+                            kind: kind,
+                            text: String::new(),
+                            page: page,
+                            end_page: end_page,
+                            top: top,
+                            end_top: end_top,
+                            source: Vec::new(),
+                        });
+                    }
+                    "text" => {
+                        if let Some(ScenePart::Dialog(Dialog { ref mut dialog, .. })) = current_part {
+                            push_dialog_text(dialog, &current_text_buffer);
+                            in_dialog_text = true;
+                        } else {
+                            return Err(invalid_structure("text outside of dialog"));
+                        }
+                    }
+                    "b" | "em" => {
+                        let style = if name.local_name == "b" { EmphasisStyle::Bold } else { EmphasisStyle::Italic };
+                        emphasis_starts.push((style, current_text_buffer.chars().count()));
 
-                try!(writer.write(XmlEvent::end_element()));
-            }
-            &ScenePart::Dialog { ref speaker, ref dialog, ref page } => {
-                try!(writer.write(XmlEvent::start_element("dialog")
-                                           .attr("character", speaker)
-                                           .attr("page", page.to_string().as_ref())));
+                        // the emphasized text continues the surrounding text
+                        continue;
+                    }
+                    "speaker" => {
+                        // the speakers are split from the character cue
+                        if let Some(ScenePart::Dialog(_)) = current_part {
+                        } else {
+                            return Err(invalid_structure("speaker outside of dialog"));
+                        }
+                    }
+                    "source_span" => {
+                        let span = try!(read_source_span(&attributes));
+                        if in_dialog_direction || in_dialog_text {
+                            dialog_part_source.push(span);
+                        } else {
+                            part_source.push(span);
+                        }
+                    }
+                    "dual_dialog" => {
+                        if current_part.is_some() || dual_dialog.is_some() {
+                            return Err(invalid_structure("dual dialog within another part"));
+                        }
+                        dual_dialog = Some(Vec::new());
+                    }
+                    "dialog" => {
+                        let mut speaker = String::new();
+                        let mut mode = Vec::new();
+                        for attr in attributes.iter() {
+                            match attr.name.local_name.as_ref() {
+                                "character" => speaker = attr.value.clone(),
+                                "mode" => mode = parse_mode(&attr.value),
+                                _ => {}
+                            }
+                        }
 
-                for (i, dialog_part) in dialog.iter().enumerate() {
-                    match dialog_part {
-                        &DialogPart::Dialog(ref dialog) => {
-                            try!(writer.write(XmlEvent::characters(dialog)));
+                        let (page, end_page, top, end_top) = try!(read_position(&attributes));
+                        current_part = Some(ScenePart::Dialog(Dialog {
+                            speaker: speaker,
+                            mode: mode,
+                            dialog: Vec::new(),
+                            page: page,
+                            end_page: end_page,
+                            top: top,
+                            end_top: end_top,
+                            revised: try!(read_revised(&attributes)),
+                            source: Vec::new(),
+                        }));
+                    }
+                    element => {
+                        return Err(invalid_structure(&format!("unknown element '{}'", element)));
+                    }
+                }
+
+                current_text_buffer.clear();
+            }
+            ReaderEvent::Whitespace(ref text) if text.contains('\n') => {
+                // indentation between the elements, which is not part of the
+                // texts of the script
+            }
+            ReaderEvent::Characters(text) | ReaderEvent::Whitespace(text) | ReaderEvent::CData(text) => {
+                current_text_buffer.push_str(&text);
+            }
+            ReaderEvent::EndElement { name } => {
+                match name.local_name.as_ref() {
+                    "b" | "em" => {
+                        if let Some((style, start)) = emphasis_starts.pop() {
+                            current_emphasis.push(Emphasis {
+                                style: style,
+                                start: start,
+                                end: current_text_buffer.chars().count(),
+                            });
                         }
-                        &DialogPart::Direction(ref direction) => {
-                            try!(writer.write(XmlEvent::start_element("direction")));
-                            try!(writer.write(XmlEvent::characters(direction)));
-                            try!(writer.write(XmlEvent::end_element()));
+                        continue;
+                    }
+                    "direction" if in_dialog_direction => {
+                        if let Some(ScenePart::Dialog(Dialog { ref mut dialog, .. })) = current_part {
+                            let direction = current_text_buffer.trim().to_string();
+                            dialog.push(DialogPart::Direction(direction,
+                                                              dialog_part_source.drain(..).collect(),
+                                                              take_emphasis(&mut current_emphasis)));
+                        }
+                        in_dialog_direction = false;
+                    }
+                    "text" => {
+                        if let Some(ScenePart::Dialog(Dialog { ref mut dialog, .. })) = current_part {
+                            let text = current_text_buffer.trim().to_string();
+                            dialog.push(DialogPart::Dialog(text,
+                                                           dialog_part_source.drain(..).collect(),
+                                                           take_emphasis(&mut current_emphasis)));
+                        }
+                        in_dialog_text = false;
+                    }
+                    "direction" | "dialog" | "transition" => {
+                        let mut part = try!(current_part.take().ok_or_else(|| {
+                            invalid_structure("unexpected end of part")
+                        }));
+                        match part {
+                            ScenePart::Direction { ref mut direction, ref mut source, ref mut emphasis, .. } => {
+                                direction.push_str(current_text_buffer.trim());
+                                source.extend(part_source.drain(..));
+                                *emphasis = take_emphasis(&mut current_emphasis);
+                            }
+                            ScenePart::Dialog(Dialog { ref mut dialog, ref mut source, .. }) => {
+                                push_dialog_text(dialog, &current_text_buffer);
+                                source.extend(part_source.drain(..));
+                            }
+                            ScenePart::DualDialog { .. } => {}
+                            ScenePart::Transition { ref mut text, ref mut source, .. } => {
+                                text.push_str(current_text_buffer.trim());
+                                source.extend(part_source.drain(..));
+                            }
+                        }
+
+                        if let Some(ref mut dialogs) = dual_dialog {
+                            if let ScenePart::Dialog(dialog) = part {
+                                dialogs.push(dialog);
+                            } else {
+                                return Err(invalid_structure("dual dialog containing other parts than dialogs"));
+                            }
+                        } else {
+                            try!(push_part(&mut script, part));
                         }
                     }
+                    "dual_dialog" => {
+                        let mut dialogs = dual_dialog.take().unwrap_or(Vec::new());
+                        if dialogs.len() != 2 {
+                            return Err(invalid_structure("dual dialog without two dialogs"));
+                        }
 
-                    if i + 1 != dialog.len() {
-                        try!(writer.write(XmlEvent::characters(" ")));
+                        let right = dialogs.pop().unwrap();
+                        let left = dialogs.pop().unwrap();
+                        try!(push_part(&mut script, ScenePart::DualDialog {
+                            left: left,
+                            right: right,
+                        }));
+                    }
+                    "title" => script.metadata.title = Some(current_text_buffer.trim().to_string()),
+                    "credit" => script.metadata.credits.push(Credit {
+                        credit: current_text_buffer.trim().to_string(),
+                        authors: Vec::new(),
+                    }),
+                    "author" => {
+                        if script.metadata.credits.is_empty() {
+                            script.metadata.credits.push(Default::default());
+                        }
+                        let credit = script.metadata.credits.last_mut().unwrap();
+                        credit.authors.push(current_text_buffer.trim().to_string());
                     }
+                    "source" => script.metadata.source = Some(current_text_buffer.trim().to_string()),
+                    "draft_date" => script.metadata.draft_date = Some(current_text_buffer.trim().to_string()),
+                    "revision" => script.metadata.revision = Some(current_text_buffer.trim().to_string()),
+                    "contact" => script.metadata.contact = Some(current_text_buffer.trim().to_string()),
+                    _ => {}
                 }
 
+                current_text_buffer.clear();
+            }
+            _ => {}
+        }
+    }
+
+    Ok(script)
+}
+
+
+use ::{Credit, Dialog, DialogPart, Emphasis, EmphasisStyle, Location, LocationType, ScenePart, Script, ScriptMetadata, SourceSpan,
+     Speaker, TransitionKind};
+use parse::ParseError;
+use serialize::{format_mode, is_single_speaker, parse_mode, parse_time_of_day};
+use std::cmp;
+use std::io::{Read, Write};
+use std::str::FromStr;
+use xml::{EventReader, EventWriter, EmitterConfig};
+use xml::attribute::OwnedAttribute;
+use xml::reader::XmlEvent as ReaderEvent;
+use xml::writer::Result as XmlResult;
+use xml::writer::XmlEvent;
+
+fn invalid_structure(message: &str) -> ParseError {
+    ParseError::InvalidStructure { message: message.to_string() }
+}
+
+/// Reads the optional numeric attribute with the given name.
+fn read_number<T: FromStr>(attributes: &Vec<OwnedAttribute>, name: &str) -> Result<Option<T>, ParseError> {
+    match attributes.iter().find(|attr| attr.name.local_name == name) {
+        Some(attr) => attr.value.parse().map(Some).map_err(|_| {
+            invalid_structure(&format!("invalid {} '{}'", name.replace('_', " "), attr.value))
+        }),
+        None => Ok(None),
+    }
+}
+
+/// Reads the pages and vertical positions of a part.
+///
+/// The page is required, while the end defaults to the start.
+fn read_position(attributes: &Vec<OwnedAttribute>) -> Result<(u32, u32, Option<i32>, Option<i32>), ParseError> {
+    let page = try!(try!(read_number(attributes, "page")).ok_or_else(|| invalid_structure("missing page")));
+    let end_page = try!(read_number(attributes, "end_page")).unwrap_or(page);
+    let top = try!(read_number(attributes, "top"));
+    let end_top = try!(read_number(attributes, "end_top")).or(top);
+
+    Ok((page, end_page, top, end_top))
+}
+
+/// Takes the `Emphasis` collected for the current text.
+fn take_emphasis(emphasis: &mut Vec<Emphasis>) -> Vec<Emphasis> {
+    let mut taken: Vec<Emphasis> = emphasis.drain(..).collect();
+    Emphasis::normalize(&mut taken);
+    taken
+}
+
+/// Reads a `SourceSpan` from the attributes of a `<source_span>` element.
+fn read_source_span(attributes: &Vec<OwnedAttribute>) -> Result<SourceSpan, ParseError> {
+    let number = |name: &str| {
+        read_number(attributes, name).and_then(|value| {
+            value.ok_or_else(|| invalid_structure(&format!("missing {} of source span", name)))
+        })
+    };
+
+    let mut lines = Vec::new();
+    if let Some(attr) = attributes.iter().find(|attr| attr.name.local_name == "lines") {
+        for line in attr.value.split_whitespace() {
+            lines.push(try!(line.parse().map_err(|_| {
+                invalid_structure(&format!("invalid line '{}' of source span", line))
+            })));
+        }
+    }
+
+    Ok(SourceSpan {
+        page: try!(number("page")) as u32,
+        top: try!(number("top")),
+        left: try!(number("left")),
+        bottom: try!(number("bottom")),
+        right: try!(number("right")),
+        lines: lines,
+    })
+}
+
+fn read_revised(attributes: &Vec<OwnedAttribute>) -> Result<bool, ParseError> {
+    match attributes.iter().find(|attr| attr.name.local_name == "revised") {
+        Some(revised) => revised.value.parse().map_err(|_| {
+            invalid_structure(&format!("invalid revised flag '{}'", revised.value))
+        }),
+        None => Ok(false),
+    }
+}
+
+/// Adds a part to the last location of the script.
+fn push_part(script: &mut Script, part: ScenePart) -> Result<(), ParseError> {
+    let location = try!(script.scenes.last_mut()
+                              .and_then(|scene| scene.last_mut())
+                              .ok_or_else(|| invalid_structure("part outside of location")));
+    location.parts.push(part);
+
+    Ok(())
+}
+
+/// Adds the text between the inline directions of a dialog.
+fn push_dialog_text(dialog: &mut Vec<DialogPart>, text: &str) {
+    let text = text.trim();
+    if text.len() > 0 {
+        dialog.push(DialogPart::Dialog(text.to_string(), Vec::new(), Vec::new()));
+    }
+}
+
+fn format_metadata<W: Write>(metadata: &ScriptMetadata, writer: &mut EventWriter<W>) -> XmlResult<()> {
+    try!(writer.write(XmlEvent::start_element("metadata")));
+
+    let mut fields: Vec<(&str, &str)> = Vec::new();
+    if let Some(ref title) = metadata.title {
+        fields.push(("title", title));
+    }
+    for (i, credit) in metadata.credits.iter().enumerate() {
+        // the credit separates the authors of the following credits
+        if credit.credit.len() > 0 || i > 0 {
+            fields.push(("credit", &credit.credit));
+        }
+        for author in credit.authors.iter() {
+            fields.push(("author", author));
+        }
+    }
+    if let Some(ref source) = metadata.source {
+        fields.push(("source", source));
+    }
+    if let Some(ref draft_date) = metadata.draft_date {
+        fields.push(("draft_date", draft_date));
+    }
+    if let Some(ref revision) = metadata.revision {
+        fields.push(("revision", revision));
+    }
+    if let Some(ref contact) = metadata.contact {
+        fields.push(("contact", contact));
+    }
+
+    for (name, text) in fields {
+        try!(writer.write(XmlEvent::start_element(name)));
+        try!(writer.write(XmlEvent::characters(text)));
+        try!(writer.write(XmlEvent::end_element()));
+    }
+
+    writer.write(XmlEvent::end_element())
+}
+
+fn format_scene_parts<W: Write>(scene_parts: &Vec<ScenePart>, writer: &mut EventWriter<W>) -> XmlResult<()> {
+    for part in scene_parts.iter() {
+        try!(format_scene_part(part, writer));
+    }
+
+    Ok(())
+}
+
+fn format_scene_part<W: Write>(part: &ScenePart, writer: &mut EventWriter<W>) -> XmlResult<()> {
+    match part {
+        &ScenePart::Direction { ref direction, page, end_page, top, end_top, revised, ref source, ref emphasis } => {
+            let position = format_position(page, end_page, top, end_top);
+            let mut direction_event = XmlEvent::start_element("direction");
+            for &(name, ref value) in position.iter() {
+                direction_event = direction_event.attr(name, value);
+            }
+            if revised {
+                direction_event = direction_event.attr("revised", "true");
+            }
+            try!(writer.write(direction_event));
+
+            try!(format_source(source, writer));
+            try!(format_text(direction, emphasis, writer));
+
+            try!(writer.write(XmlEvent::end_element()));
+        }
+        &ScenePart::Dialog(ref dialog) => try!(format_dialog(dialog, writer)),
+        &ScenePart::DualDialog { ref left, ref right } => {
+            try!(writer.write(XmlEvent::start_element("dual_dialog")));
+            try!(format_dialog(left, writer));
+            try!(format_dialog(right, writer));
+            try!(writer.write(XmlEvent::end_element()));
+        }
+        &ScenePart::Transition { ref kind, ref text, page, end_page, top, end_top, ref source } => {
+            let position = format_position(page, end_page, top, end_top);
+            let mut transition_event = XmlEvent::start_element("transition")
+                                                .attr("kind", kind.as_str());
+            for &(name, ref value) in position.iter() {
+                transition_event = transition_event.attr(name, value);
+            }
+            try!(writer.write(transition_event));
+
+            try!(format_source(source, writer));
+            try!(writer.write(XmlEvent::characters(text)));
+
+            try!(writer.write(XmlEvent::end_element()));
+        }
+    }
+
+    Ok(())
+}
+
+fn format_dialog<W: Write>(dialog: &Dialog, writer: &mut EventWriter<W>) -> XmlResult<()> {
+    let speakers = dialog.speakers();
+    let &Dialog { ref speaker, ref mode, ref dialog, page, end_page, top, end_top, revised, ref source } = dialog;
+
+    let mode = format_mode(mode);
+    let position = format_position(page, end_page, top, end_top);
+    let mut dialog_event = XmlEvent::start_element("dialog")
+                                    .attr("character", speaker);
+    if mode.len() > 0 {
+        dialog_event = dialog_event.attr("mode", &mode);
+    }
+    for &(name, ref value) in position.iter() {
+        dialog_event = dialog_event.attr(name, value);
+    }
+    if revised {
+        dialog_event = dialog_event.attr("revised", "true");
+    }
+    try!(writer.write(dialog_event));
+    if !is_single_speaker(speaker, &speakers) {
+        try!(format_speakers(&speakers, writer));
+    }
+    try!(format_source(source, writer));
+
+    for (i, dialog_part) in dialog.iter().enumerate() {
+        match dialog_part {
+            &DialogPart::Dialog(ref dialog, ref source, ref emphasis)
+                if source.is_empty() && emphasis.is_empty() => {
+                try!(writer.write(XmlEvent::characters(dialog)));
+            }
+            &DialogPart::Dialog(ref dialog, ref source, ref emphasis) => {
+                try!(writer.write(XmlEvent::start_element("text")));
+                try!(format_source(source, writer));
+                try!(format_text(dialog, emphasis, writer));
+                try!(writer.write(XmlEvent::end_element()));
+            }
+            &DialogPart::Direction(ref direction, ref source, ref emphasis) => {
+                try!(writer.write(XmlEvent::start_element("direction")));
+                try!(format_source(source, writer));
+                try!(format_text(direction, emphasis, writer));
                 try!(writer.write(XmlEvent::end_element()));
             }
         }
+
+        if i + 1 != dialog.len() {
+            try!(writer.write(XmlEvent::characters(" ")));
+        }
+    }
+
+    writer.write(XmlEvent::end_element())
+}
+
+/// Returns the attributes for the pages and vertical positions of a part.
+///
+/// The end is only written if it differs from the start and the positions
+/// only if they are known.
+fn format_position(page: u32, end_page: u32, top: Option<i32>, end_top: Option<i32>) -> Vec<(&'static str, String)> {
+    let mut attributes = vec![("page", page.to_string())];
+    if end_page != page {
+        attributes.push(("end_page", end_page.to_string()));
+    }
+    if let Some(top) = top {
+        attributes.push(("top", top.to_string()));
+    }
+    if let Some(end_top) = end_top {
+        if end_page != page || Some(end_top) != top {
+            attributes.push(("end_top", end_top.to_string()));
+        }
+    }
+
+    attributes
+}
+
+/// Writes the `Speaker`s of a dialog as `<speaker>` elements.
+fn format_speakers<W: Write>(speakers: &[Speaker], writer: &mut EventWriter<W>) -> XmlResult<()> {
+    for speaker in speakers.iter() {
+        let mut speaker_event = XmlEvent::start_element("speaker").attr("name", speaker.name());
+        if speaker.is_group() {
+            speaker_event = speaker_event.attr("group", "true");
+        }
+        try!(writer.write(speaker_event));
+        try!(writer.write(XmlEvent::end_element()));
+    }
+
+    Ok(())
+}
+
+/// Writes the `SourceSpan`s of a part as `<source_span>` elements.
+fn format_source<W: Write>(source: &[SourceSpan], writer: &mut EventWriter<W>) -> XmlResult<()> {
+    for span in source.iter() {
+        let page = span.page.to_string();
+        let top = span.top.to_string();
+        let left = span.left.to_string();
+        let bottom = span.bottom.to_string();
+        let right = span.right.to_string();
+        let lines = span.lines.iter().map(|line| line.to_string()).collect::<Vec<_>>().join(" ");
+
+        try!(writer.write(XmlEvent::start_element("source_span")
+                                   .attr("page", &page)
+                                   .attr("top", &top)
+                                   .attr("left", &left)
+                                   .attr("bottom", &bottom)
+                                   .attr("right", &right)
+                                   .attr("lines", &lines)));
+        try!(writer.write(XmlEvent::end_element()));
+    }
+
+    Ok(())
+}
+
+/// Writes a text with its `Emphasis` as `<b>` and `<em>` elements.
+///
+/// Overlapping ranges of different styles are split, so that the elements
+/// are nested properly.
+fn format_text<W: Write>(text: &str, emphasis: &[Emphasis], writer: &mut EventWriter<W>) -> XmlResult<()> {
+    let chars: Vec<char> = text.chars().collect();
+
+    let mut boundaries = vec![0, chars.len()];
+    for range in emphasis.iter() {
+        boundaries.push(cmp::min(range.start, chars.len()));
+        boundaries.push(cmp::min(range.end, chars.len()));
+    }
+    boundaries.sort();
+    boundaries.dedup();
+
+    let mut open_styles: Vec<EmphasisStyle> = Vec::new();
+    for bounds in boundaries.windows(2) {
+        let (start, end) = (bounds[0], bounds[1]);
+        let mut styles: Vec<EmphasisStyle> = emphasis.iter()
+                                                     .filter(|range| range.start <= start && end <= range.end)
+                                                     .map(|range| range.style)
+                                                     .collect();
+        styles.sort();
+        styles.dedup();
+
+        // close the elements of the styles ending here and all within them
+        let kept = open_styles.iter().take_while(|style| styles.contains(style)).count();
+        while open_styles.len() > kept {
+            open_styles.pop();
+            try!(writer.write(XmlEvent::end_element()));
+        }
+
+        for style in styles {
+            if !open_styles.contains(&style) {
+                let element = match style {
+                    EmphasisStyle::Bold => "b",
+                    EmphasisStyle::Italic => "em",
+                };
+                try!(writer.write(XmlEvent::start_element(element)));
+                open_styles.push(style);
+            }
+        }
+
+        let segment: String = chars[start..end].iter().cloned().collect();
+        try!(writer.write(XmlEvent::characters(&segment)));
+    }
+
+    for _ in open_styles {
+        try!(writer.write(XmlEvent::end_element()));
     }
 
     Ok(())
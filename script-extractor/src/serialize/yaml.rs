@@ -0,0 +1,37 @@
+//! Serialize `Script`s to `yaml`
+
+/// Serialize the given `Script` into a `yaml`
+///
+/// Serialize the given `Script` into a `yaml` document with the same
+/// structure as the `json` written by `serialize::json::format_script`.
+///
+/// # Example
+///
+/// ```yaml
+/// ---
+/// metadata:
+///   title: Sintel
+///   credits:
+///     - credit: Written by
+///       authors:
+///         - Esther Wouda
+/// scenes:
+///   - - place: Snowy Landscape - Day
+///       type: external
+///       parts:
+///         - page: 2
+///           character: Shaman
+///           mode: VO
+///           dialog:
+///             - direction: (To Sintel)
+///             - "Here, take a sip."
+/// ```
+pub fn format_script<W: Write>(script: &Script, output: &mut W) -> io::Result<()> {
+    try!(serde_yaml::to_writer(&mut *output, script).map_err(|e| io::Error::new(io::ErrorKind::Other, e)));
+    output.flush()
+}
+
+
+use ::Script;
+use serde_yaml;
+use std::io::{self, Write};